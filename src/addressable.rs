@@ -42,4 +42,9 @@ pub trait Addressable {
 pub trait PpuAddressable {
     fn ppu_read8(&mut self, address: Address) -> Result<u8>;
     fn ppu_write8(&mut self, address: Address, data: u8) -> Result<()>;
+    // Changes whenever the CHR data mapped into the pattern tables is
+    // switched, so cached tiles can be discarded. Plain memory never banks.
+    fn pattern_table_generation(&self) -> usize {
+        0
+    }
 }
@@ -0,0 +1,202 @@
+use addressable::Address;
+use pulse::{Pulse, PulseChannel};
+use triangle::Triangle;
+use noise::Noise;
+use dmc::Dmc;
+use frame_counter::FrameCounter;
+use mixer::{Mixer, DEFAULT_SAMPLE_RATE};
+use image::TvSystem;
+
+// Register offsets from $4000
+const PULSE_1_START: Address = 0x00;
+const PULSE_1_END: Address = 0x03;
+const PULSE_2_START: Address = 0x04;
+const PULSE_2_END: Address = 0x07;
+const TRIANGLE_START: Address = 0x08;
+const TRIANGLE_END: Address = 0x0b;
+const NOISE_START: Address = 0x0c;
+const NOISE_END: Address = 0x0f;
+const DMC_START: Address = 0x10;
+const DMC_END: Address = 0x13;
+pub const STATUS: Address = 0x15;
+pub const FRAME_COUNTER: Address = 0x17;
+
+const STATUS_PULSE_1: u8 = bit!(0);
+const STATUS_PULSE_2: u8 = bit!(1);
+const STATUS_TRIANGLE: u8 = bit!(2);
+const STATUS_NOISE: u8 = bit!(3);
+const STATUS_DMC: u8 = bit!(4);
+const STATUS_FRAME_INTERRUPT: u8 = bit!(6);
+const STATUS_DMC_INTERRUPT: u8 = bit!(7);
+
+// The level of each channel at the current cycle, for the mixer
+#[derive(Debug, Clone, Copy)]
+pub struct ApuOutput {
+    pub pulse_1: u8,
+    pub pulse_2: u8,
+    pub triangle: u8,
+    pub noise: u8,
+    pub dmc: u8,
+    // cartridge expansion audio, already scaled to the mixed output
+    pub expansion: f32,
+}
+
+pub struct Apu {
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+    mixer: Mixer,
+    expansion: f32,
+    // CPU cycles since power on
+    cycle: u64,
+}
+
+impl Apu {
+    pub fn new(tv_system: TvSystem) -> Self {
+        Apu {
+            pulse_1: Pulse::new(PulseChannel::One),
+            pulse_2: Pulse::new(PulseChannel::Two),
+            triangle: Triangle::new(),
+            noise: Noise::new(tv_system),
+            dmc: Dmc::new(tv_system),
+            frame_counter: FrameCounter::new(tv_system),
+            mixer: Mixer::new(tv_system, DEFAULT_SAMPLE_RATE),
+            expansion: 0.0,
+            cycle: 0,
+        }
+    }
+
+    pub fn write8(&mut self, address: Address, data: u8) {
+        match address {
+            PULSE_1_START...PULSE_1_END => self.pulse_1.write((address - PULSE_1_START) as usize, data),
+            PULSE_2_START...PULSE_2_END => self.pulse_2.write((address - PULSE_2_START) as usize, data),
+            TRIANGLE_START...TRIANGLE_END => self.triangle.write((address - TRIANGLE_START) as usize, data),
+            NOISE_START...NOISE_END => self.noise.write((address - NOISE_START) as usize, data),
+            DMC_START...DMC_END => self.dmc.write((address - DMC_START) as usize, data),
+            STATUS => {
+                self.pulse_1.set_enabled(data & STATUS_PULSE_1 != 0);
+                self.pulse_2.set_enabled(data & STATUS_PULSE_2 != 0);
+                self.triangle.set_enabled(data & STATUS_TRIANGLE != 0);
+                self.noise.set_enabled(data & STATUS_NOISE != 0);
+                self.dmc.set_enabled(data & STATUS_DMC != 0);
+            }
+            FRAME_COUNTER => self.frame_counter.write(data, self.cycle % 2 == 1),
+            _ => {}
+        }
+    }
+
+    // Reads $4015, reporting which channels are still playing and
+    // acknowledging the frame interrupt
+    pub fn read_status(&mut self) -> u8 {
+        let mut status = 0;
+        if self.pulse_1.active() {
+            status |= STATUS_PULSE_1;
+        }
+        if self.pulse_2.active() {
+            status |= STATUS_PULSE_2;
+        }
+        if self.triangle.active() {
+            status |= STATUS_TRIANGLE;
+        }
+        if self.noise.active() {
+            status |= STATUS_NOISE;
+        }
+        if self.dmc.active() {
+            status |= STATUS_DMC;
+        }
+        if self.frame_counter.irq() {
+            status |= STATUS_FRAME_INTERRUPT;
+        }
+        if self.dmc.irq() {
+            status |= STATUS_DMC_INTERRUPT;
+        }
+        self.frame_counter.clear_irq();
+        status
+    }
+
+    // Advances the APU by one CPU cycle
+    pub fn tick(&mut self) {
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+
+        let clock = self.frame_counter.tick();
+        if clock.quarter {
+            self.clock_quarter_frame();
+        }
+        if clock.half {
+            self.clock_half_frame();
+        }
+
+        // the pulse timers are clocked at half the CPU rate
+        if self.cycle % 2 == 1 {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+
+        let output = self.output();
+        self.mixer.update(self.cycle, &output);
+
+        self.cycle += 1;
+    }
+
+    // Sets the cartridge's sound level for the following cycles
+    pub fn set_expansion_output(&mut self, level: f32) {
+        self.expansion = level;
+    }
+
+    pub fn mixer(&mut self) -> &mut Mixer {
+        &mut self.mixer
+    }
+
+    // Produces the audio samples for everything since the last call
+    pub fn end_frame(&mut self) {
+        self.mixer.end_frame(self.cycle);
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        self.mixer.samples()
+    }
+
+    // Whether the APU is asserting the CPU's IRQ line
+    pub fn irq(&self) -> bool {
+        self.dmc.irq() || self.frame_counter.irq()
+    }
+
+    // See `Dmc::dma_address`
+    pub fn dmc_dma_address(&self) -> Option<Address> {
+        self.dmc.dma_address()
+    }
+
+    pub fn dmc_dma_complete(&mut self, data: u8) {
+        self.dmc.dma_complete(data);
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.clock_quarter_frame();
+        self.pulse_2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+
+    pub fn output(&self) -> ApuOutput {
+        ApuOutput {
+            pulse_1: self.pulse_1.output(),
+            pulse_2: self.pulse_2.output(),
+            triangle: self.triangle.output(),
+            noise: self.noise.output(),
+            dmc: self.dmc.output(),
+            expansion: self.expansion,
+        }
+    }
+}
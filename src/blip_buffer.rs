@@ -0,0 +1,108 @@
+use std::f64::consts::PI;
+
+// Each step is spread over this many output samples
+const KERNEL_WIDTH: usize = 16;
+// Number of sub-sample positions a step can be placed at
+const KERNEL_PHASES: usize = 64;
+// Cutoff of the interpolation filter, as a fraction of the output Nyquist
+// frequency, leaving room for the filter's transition band
+const CUTOFF: f64 = 0.9;
+
+// Converts a signal given as a series of steps at a high clock rate into
+// samples at a lower rate, using band-limited steps so that the edges of
+// square waves don't alias.
+pub struct BlipBuffer {
+    clocks_per_sample: f64,
+    // fractional position of the start of the frame in `deltas`
+    offset: f64,
+    // the derivative of the output, with band-limited impulses added at
+    // each step; integrating it gives the output
+    deltas: Vec<f32>,
+    kernel: Vec<[f32; KERNEL_WIDTH]>,
+    integrator: f32,
+}
+
+impl BlipBuffer {
+    pub fn new(clock_rate: f64, sample_rate: f64) -> Self {
+        BlipBuffer {
+            clocks_per_sample: clock_rate / sample_rate,
+            offset: 0.0,
+            deltas: vec![0.0; KERNEL_WIDTH],
+            kernel: Self::make_kernel(),
+            integrator: 0.0,
+        }
+    }
+
+    // Changes the ratio of clocks to samples. Steps already added keep
+    // their positions, so this can be done between frames without a click.
+    pub fn set_rates(&mut self, clock_rate: f64, sample_rate: f64) {
+        self.clocks_per_sample = clock_rate / sample_rate;
+    }
+
+    // A Blackman-windowed sinc impulse for each phase, normalized so that
+    // every step reaches exactly its full height.
+    fn make_kernel() -> Vec<[f32; KERNEL_WIDTH]> {
+        let half_width = (KERNEL_WIDTH / 2) as f64;
+
+        (0..KERNEL_PHASES).map(|phase| {
+            let fraction = phase as f64 / KERNEL_PHASES as f64;
+            let mut impulse = [0.0; KERNEL_WIDTH];
+            let mut sum = 0.0;
+
+            for (k, value) in impulse.iter_mut().enumerate() {
+                let t = k as f64 - half_width - fraction;
+                let x = CUTOFF * PI * t;
+                let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+                let w = (t + half_width) / (2.0 * half_width);
+                let window = if w < 0.0 || w > 1.0 {
+                    0.0
+                } else {
+                    0.42 - 0.5 * (2.0 * PI * w).cos() + 0.08 * (4.0 * PI * w).cos()
+                };
+                *value = sinc * window;
+                sum += *value;
+            }
+
+            let mut normalized = [0.0; KERNEL_WIDTH];
+            for (n, &value) in normalized.iter_mut().zip(impulse.iter()) {
+                *n = (value / sum) as f32;
+            }
+            normalized
+        }).collect()
+    }
+
+    // Adds a step of height `delta` at the given clock, counted from the
+    // start of the current frame
+    pub fn add_delta(&mut self, clock: u64, delta: f32) {
+        let position = self.offset + clock as f64 / self.clocks_per_sample;
+        let index = position as usize;
+        let phase = ((position - index as f64) * KERNEL_PHASES as f64) as usize;
+
+        if self.deltas.len() < index + KERNEL_WIDTH {
+            self.deltas.resize(index + KERNEL_WIDTH, 0.0);
+        }
+
+        for (d, &k) in self.deltas[index..(index + KERNEL_WIDTH)].iter_mut().zip(self.kernel[phase].iter()) {
+            *d += delta * k;
+        }
+    }
+
+    // Ends a frame lasting `clocks` clocks, appending the samples it
+    // completed to `output`
+    pub fn end_frame(&mut self, clocks: u64, output: &mut Vec<f32>) {
+        let end = self.offset + clocks as f64 / self.clocks_per_sample;
+        let count = end as usize;
+
+        if self.deltas.len() < count + KERNEL_WIDTH {
+            self.deltas.resize(count + KERNEL_WIDTH, 0.0);
+        }
+
+        for &delta in self.deltas[..count].iter() {
+            self.integrator += delta;
+            output.push(self.integrator);
+        }
+
+        self.deltas.drain(..count);
+        self.offset = end - count as f64;
+    }
+}
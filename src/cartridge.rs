@@ -8,6 +8,9 @@ pub type Result<T> = result::Result<T, Error>;
 
 // Mapper Numbers
 pub const NROM: usize = 0;
+pub const VRC6A: usize = 24;
+pub const VRC6B: usize = 26;
+pub const FME7: usize = 69;
 
 #[derive(Debug)]
 pub enum Error {
@@ -51,14 +54,49 @@ pub trait PpuInterface {
     fn pattern_table_write(&mut self, address: Address, data: u8) -> addressable::Result<()>;
     fn name_table_read(&mut self, address: Address, ram: &mut NesVram) -> addressable::Result<u8>;
     fn name_table_write(&mut self, address: Address, data: u8, ram: &mut NesVram) -> addressable::Result<()>;
+    // Mappers with switchable CHR banks increment this on every bank switch
+    fn pattern_table_generation(&self) -> usize {
+        0
+    }
 }
 
 pub trait CartridgePpuAddressable {
     fn ppu_read8(&mut self, address: Address, ram: &mut NesVram) -> addressable::Result<u8>;
     fn ppu_write8(&mut self, address: Address, data: u8, ram: &mut NesVram) -> addressable::Result<()>;
+    fn pattern_table_generation(&self) -> usize;
 }
 
-pub trait Cartridge: Addressable + CartridgePpuAddressable {}
+// Sound chips on the cartridge, which the console mixes with the APU's
+// output through the cartridge connector
+pub trait CartridgeAudio {
+    // Clocks the sound hardware by one CPU cycle
+    fn audio_tick(&mut self) {}
+
+    // The level of the cartridge's sound, on the same scale as the APU's
+    // mixed output, where a pulse channel at full volume is about 0.15
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
+}
+
+pub trait Cartridge: Addressable + CartridgePpuAddressable + CartridgeAudio {
+    // $4020-$5fff, which few cartridges decode; None leaves the open bus value
+    fn expansion_read(&mut self, address: Address) -> addressable::Result<Option<u8>> {
+        Err(addressable::Error::UnimplementedRead(address))
+    }
+
+    fn expansion_write(&mut self, address: Address, _: u8) -> addressable::Result<()> {
+        Err(addressable::Error::UnimplementedWrite(address))
+    }
+
+    // Clocks any mapper hardware that counts CPU cycles, such as IRQ timers
+    fn cpu_tick(&mut self) {}
+
+    // Whether the cartridge is asserting the CPU's IRQ line
+    fn irq(&self) -> bool {
+        false
+    }
+}
 
 impl<C: CpuInterface> Addressable for C {
     fn read8(&mut self, address: Address) -> addressable::Result<u8> {
@@ -106,4 +144,8 @@ impl<P: PpuInterface> CartridgePpuAddressable for P {
             _ => Err(addressable::Error::BusErrorWrite(address)),
         }
     }
+
+    fn pattern_table_generation(&self) -> usize {
+        PpuInterface::pattern_table_generation(self)
+    }
 }
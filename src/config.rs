@@ -0,0 +1,162 @@
+use std::{fs, io, result};
+use std::io::Read;
+use std::collections::BTreeMap;
+
+// A small subset of TOML: [sections] containing `key = value` pairs, where
+// values are booleans, integers, floats or double-quoted strings.
+
+#[derive(Debug)]
+pub enum Error {
+    IoError(io::Error),
+    InvalidLine(usize),
+    InvalidValue(usize),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+pub struct Config {
+    sections: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config {
+            sections: BTreeMap::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut config = Config::new();
+        let mut section = String::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                if !line.ends_with(']') {
+                    return Err(Error::InvalidLine(line_number));
+                }
+                section = line[1..(line.len() - 1)].trim().to_string();
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = match parts.next() {
+                Some(v) => try!(parse_value(v.trim()).ok_or(Error::InvalidValue(line_number))),
+                None => return Err(Error::InvalidLine(line_number)),
+            };
+
+            if key.is_empty() {
+                return Err(Error::InvalidLine(line_number));
+            }
+
+            config.sections
+                .entry(section.clone())
+                .or_insert_with(BTreeMap::new)
+                .insert(unquote(key).to_string(), value);
+        }
+
+        Ok(config)
+    }
+
+    pub fn parse_file(mut file: fs::File) -> Result<Self> {
+        let mut text = String::new();
+
+        if let Err(e) = file.read_to_string(&mut text) {
+            return Err(Error::IoError(e));
+        }
+
+        Self::parse(&text)
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&Value> {
+        self.sections.get(section).and_then(|s| s.get(key))
+    }
+
+    pub fn section(&self, section: &str) -> Option<&BTreeMap<String, Value>> {
+        self.sections.get(section)
+    }
+
+    pub fn get_bool(&self, section: &str, key: &str) -> Option<bool> {
+        match self.get(section, key) {
+            Some(&Value::Bool(b)) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn get_integer(&self, section: &str, key: &str) -> Option<i64> {
+        match self.get(section, key) {
+            Some(&Value::Integer(i)) => Some(i),
+            _ => None,
+        }
+    }
+
+    // integers are accepted wherever a float is expected
+    pub fn get_float(&self, section: &str, key: &str) -> Option<f64> {
+        match self.get(section, key) {
+            Some(&Value::Float(f)) => Some(f),
+            Some(&Value::Integer(i)) => Some(i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, section: &str, key: &str) -> Option<&str> {
+        match self.get(section, key) {
+            Some(&Value::String(ref s)) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unquote(s: &str) -> &str {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        &s[1..(s.len() - 1)]
+    } else {
+        s
+    }
+}
+
+fn parse_value(s: &str) -> Option<Value> {
+    if s == "true" {
+        return Some(Value::Bool(true));
+    }
+    if s == "false" {
+        return Some(Value::Bool(false));
+    }
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        return Some(Value::String(unquote(s).to_string()));
+    }
+    if let Ok(i) = s.parse::<i64>() {
+        return Some(Value::Integer(i));
+    }
+    if let Ok(f) = s.parse::<f64>() {
+        return Some(Value::Float(f));
+    }
+    None
+}
@@ -0,0 +1,56 @@
+use input::InputDevice;
+
+pub const BUTTON_A: u8 = bit!(0);
+pub const BUTTON_B: u8 = bit!(1);
+pub const BUTTON_SELECT: u8 = bit!(2);
+pub const BUTTON_START: u8 = bit!(3);
+pub const BUTTON_UP: u8 = bit!(4);
+pub const BUTTON_DOWN: u8 = bit!(5);
+pub const BUTTON_LEFT: u8 = bit!(6);
+pub const BUTTON_RIGHT: u8 = bit!(7);
+
+// A standard pad. The frontend keeps the set of held buttons up to date,
+// and the game latches it into a shift register to read one bit at a time.
+pub struct Controller {
+    buttons: u8,
+    shift_register: u8,
+    strobe: bool,
+}
+
+impl Controller {
+    pub fn new() -> Self {
+        Controller {
+            buttons: 0,
+            shift_register: 0,
+            strobe: false,
+        }
+    }
+}
+
+impl InputDevice for Controller {
+    fn write_strobe(&mut self, strobe: bool) {
+        // the buttons are latched for as long as the strobe is high, so the
+        // last of them are kept when it falls
+        if strobe || self.strobe {
+            self.shift_register = self.buttons;
+        }
+        self.strobe = strobe;
+    }
+
+    fn read(&mut self) -> u8 {
+        // while strobed, the register keeps reloading, so only A is seen
+        if self.strobe {
+            return self.buttons & bit!(0);
+        }
+        let data = self.shift_register & bit!(0);
+        // ones are shifted in behind the buttons
+        self.shift_register = (self.shift_register >> 1) | bit!(7);
+        data
+    }
+
+    fn set_buttons(&mut self, pad: usize, buttons: u8) {
+        if pad == 0 {
+            self.buttons = buttons;
+        }
+    }
+}
@@ -222,12 +222,15 @@ impl fmt::Display for RegisterFile {
 #[derive(Clone, Copy)]
 pub struct InterruptState {
     pub nmi: bool,
+    // level-triggered, held by any device requesting an interrupt
+    pub irq: bool,
 }
 
 impl InterruptState {
     fn new() -> Self {
         InterruptState {
             nmi: false,
+            irq: false,
         }
     }
 }
@@ -237,6 +240,10 @@ pub struct Cpu {
     pub registers: RegisterFile,
     pub interrupts: InterruptState,
     pub count: u64,
+    // total CPU cycles executed
+    pub cycles: u64,
+    // set when the last indexed address calculation crossed a page
+    page_crossed: bool,
 }
 
 impl fmt::Display for Cpu {
@@ -248,30 +255,61 @@ impl fmt::Display for Cpu {
 
 const RESET_VECTOR: Address = 0xfffc;
 const NMI_VECTOR: Address = 0xfffa;
+const IRQ_VECTOR: Address = 0xfffe;
 const STACK_PAGE_BOTTOM: Address = 0x0100;
 
+const INTERRUPT_CYCLES: u64 = 7;
+
 impl Cpu {
     pub fn new() -> Self {
         Cpu {
             registers: RegisterFile::new(),
             interrupts: InterruptState::new(),
             count: 0,
+            cycles: 0,
+            page_crossed: false,
         }
     }
 
     pub fn init<Memory: Addressable>(&mut self, memory: &mut Memory) -> Result<()> {
         self.registers.program_counter = try!(memory.read16_le(RESET_VECTOR)
             .map_err(Error::MemoryError));
+        // interrupts are disabled on reset
+        self.set_disable_interrupt_status();
+
+        Ok(())
+    }
 
+    // Sets up a call to the subroutine with the given accumulator and X
+    // register, as though by a JSR at the given return address, less one
+    pub fn call<Memory: Addressable>(&mut self,
+                                     address: Address,
+                                     accumulator: u8,
+                                     x_index: u8,
+                                     return_address: Address,
+                                     memory: &mut Memory) -> Result<()> {
+        try!(self.push16_le(return_address.wrapping_sub(1), memory));
+        self.registers.accumulator = accumulator;
+        self.registers.x_index = x_index;
+        self.registers.program_counter = address;
         Ok(())
     }
 
+    pub fn program_counter(&self) -> Address {
+        self.registers.program_counter
+    }
+
     pub fn tick<Memory: Addressable>(&mut self, memory: &mut Memory) -> Result<()> {
         if self.interrupts.nmi {
             try!(self.nmi(memory));
+            self.cycles += INTERRUPT_CYCLES;
+        } else if self.irq_pending() {
+            try!(self.irq(memory));
+            self.cycles += INTERRUPT_CYCLES;
         }
 
         let opcode = try!(self.fetch8(memory));
+        self.cycles += instruction::cycles(opcode) as u64;
 
         let instruction = try!(Self::decode_instruction(opcode));
 
@@ -301,6 +339,25 @@ impl Cpu {
         Ok(())
     }
 
+    fn irq_pending(&self) -> bool {
+        self.interrupts.irq && !self.registers.status.irq_disable
+    }
+
+    // Whether an interrupt will be taken before the next instruction
+    pub fn interrupt_pending(&self) -> bool {
+        self.interrupts.nmi || self.irq_pending()
+    }
+
+    fn irq<Memory: Addressable>(&mut self, memory: &mut Memory) -> Result<()> {
+        try!(self.interrupt_common(memory));
+        self.set_disable_interrupt_status();
+
+        self.registers.program_counter = try!(memory.read16_le(IRQ_VECTOR)
+                                              .map_err(Error::MemoryError));
+
+        Ok(())
+    }
+
     fn decode_instruction(opcode: u8) -> Result<Instruction> {
         match Instruction::decode(opcode) {
             Ok(i) => Ok(i),
@@ -334,6 +391,11 @@ impl Cpu {
             MemoryAddressingMode::Immediate => self.fetch8(memory),
             _ => {
                 let address = try!(self.addressing_mode_address(mode, memory));
+                // reads take an extra cycle to fix up the high byte of the
+                // address, while writes always spend it
+                if self.page_crossed {
+                    self.cycles += 1;
+                }
                 memory.read8(address).map_err(Error::MemoryError)
             }
         }
@@ -352,15 +414,23 @@ impl Cpu {
                                                     mode: MemoryAddressingMode,
                                                     memory: &mut Memory)
                                                     -> Result<Address> {
+        self.page_crossed = false;
+
         let address = match mode {
             MemoryAddressingMode::ZeroPage => try!(self.fetch8(memory)) as u16,
             MemoryAddressingMode::Absolute => try!(self.fetch16_le(memory)),
-            MemoryAddressingMode::AbsoluteXIndexed => try!(self.fetch16_le(memory)).wrapping_add(self.registers.x_index as u16),
-            MemoryAddressingMode::AbsoluteYIndexed => try!(self.fetch16_le(memory)).wrapping_add(self.registers.y_index as u16),
+            MemoryAddressingMode::AbsoluteXIndexed => {
+                let base = try!(self.fetch16_le(memory));
+                self.index_address(base, self.registers.x_index)
+            }
+            MemoryAddressingMode::AbsoluteYIndexed => {
+                let base = try!(self.fetch16_le(memory));
+                self.index_address(base, self.registers.y_index)
+            }
             MemoryAddressingMode::IndirectYIndexed => {
                 let address_ptr = try!(self.fetch8(memory)) as u16;
-                try!(memory.read16_le(address_ptr).map_err(Error::MemoryError))
-                    .wrapping_add(self.registers.y_index as u16)
+                let base = try!(memory.read16_le(address_ptr).map_err(Error::MemoryError));
+                self.index_address(base, self.registers.y_index)
             }
             MemoryAddressingMode::ZeroPageXIndexed => try!(self.fetch8(memory)).wrapping_add(self.registers.x_index) as u16,
             MemoryAddressingMode::ZeroPageYIndexed => try!(self.fetch8(memory)).wrapping_add(self.registers.y_index) as u16,
@@ -370,6 +440,13 @@ impl Cpu {
         Ok(address)
     }
 
+    fn index_address(&mut self, base: Address, index: u8) -> Address {
+        let address = base.wrapping_add(index as u16);
+        self.page_crossed = address & 0xff00 != base & 0xff00;
+        address
+    }
+
+    // Taken branches cost an extra cycle, and another if they cross a page
     fn relative_branch(&mut self, offset: u8) {
         // Casts allow negative signed 8-bit value to be correctly
         // added to unsigned 16-bit program counter.
@@ -380,6 +457,11 @@ impl Cpu {
 
         let pc = self.registers.program_counter;
         self.registers.program_counter = pc.wrapping_add(offset);
+
+        self.cycles += 1;
+        if self.registers.program_counter & 0xff00 != pc & 0xff00 {
+            self.cycles += 1;
+        }
     }
 
     fn emulate_instruction<Memory: Addressable>(&mut self,
@@ -0,0 +1,201 @@
+use addressable::Address;
+use image::TvSystem;
+
+const FLAGS_RATE: usize = 0;
+const DIRECT_LOAD: usize = 1;
+const SAMPLE_ADDRESS: usize = 2;
+const SAMPLE_LENGTH: usize = 3;
+
+const FLAGS_IRQ_ENABLED: u8 = bit!(7);
+const FLAGS_LOOP: u8 = bit!(6);
+const RATE_INDEX_MASK: u8 = mask!(4);
+
+const OUTPUT_LEVEL_MASK: u8 = mask!(7);
+const MAX_OUTPUT_LEVEL: u8 = 127;
+const OUTPUT_STEP: u8 = 2;
+
+// samples live in the top quarter of the address space, and the address
+// wraps back to $8000 after $ffff
+const SAMPLE_ADDRESS_BASE: Address = 0xc000;
+const SAMPLE_ADDRESS_STRIDE: Address = 64;
+const SAMPLE_ADDRESS_WRAP: Address = 0x8000;
+const SAMPLE_LENGTH_STRIDE: usize = 16;
+
+const BITS_PER_SAMPLE_BYTE: u8 = 8;
+
+// Timer periods in CPU cycles
+const NTSC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+const PAL_RATES: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+// Delta modulation channel, which plays 1-bit delta encoded samples read
+// from the CPU's address space
+pub struct Dmc {
+    rates: &'static [u16; 16],
+    irq_enabled: bool,
+    irq: bool,
+    looping: bool,
+    timer_period: u16,
+    timer: u16,
+
+    // memory reader
+    sample_address: Address,
+    sample_length: usize,
+    current_address: Address,
+    bytes_remaining: usize,
+    sample_buffer: Option<u8>,
+
+    // output unit
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    output_level: u8,
+}
+
+impl Dmc {
+    pub fn new(tv_system: TvSystem) -> Self {
+        let rates = match tv_system {
+            TvSystem::Ntsc => &NTSC_RATES,
+            TvSystem::Pal => &PAL_RATES,
+        };
+
+        Dmc {
+            rates: rates,
+            irq_enabled: false,
+            irq: false,
+            looping: false,
+            timer_period: rates[0],
+            timer: 0,
+            sample_address: SAMPLE_ADDRESS_BASE,
+            sample_length: 1,
+            current_address: SAMPLE_ADDRESS_BASE,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: BITS_PER_SAMPLE_BYTE,
+            silence: true,
+            output_level: 0,
+        }
+    }
+
+    // Writes one of the channel's 4 registers
+    pub fn write(&mut self, register: usize, data: u8) {
+        match register {
+            FLAGS_RATE => {
+                self.irq_enabled = data & FLAGS_IRQ_ENABLED != 0;
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+                self.looping = data & FLAGS_LOOP != 0;
+                self.timer_period = self.rates[(data & RATE_INDEX_MASK) as usize];
+            }
+            DIRECT_LOAD => {
+                self.output_level = data & OUTPUT_LEVEL_MASK;
+            }
+            SAMPLE_ADDRESS => {
+                self.sample_address = SAMPLE_ADDRESS_BASE + data as Address * SAMPLE_ADDRESS_STRIDE;
+            }
+            SAMPLE_LENGTH => {
+                self.sample_length = data as usize * SAMPLE_LENGTH_STRIDE + 1;
+            }
+            _ => {}
+        }
+    }
+
+    // Enabling restarts the sample only if it has finished, while disabling
+    // stops it after the byte currently being played
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    // The address of the next sample byte, if the reader needs one. The
+    // caller fetches it over the CPU bus, stalling the CPU, and passes it
+    // to `dma_complete`.
+    pub fn dma_address(&self) -> Option<Address> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    pub fn dma_complete(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+
+        self.current_address = if self.current_address == 0xffff {
+            SAMPLE_ADDRESS_WRAP
+        } else {
+            self.current_address + 1
+        };
+
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    // Clocked every CPU cycle
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            self.clock_output();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_output(&mut self) {
+        if !self.silence {
+            if self.shift_register & bit!(0) != 0 {
+                if self.output_level <= MAX_OUTPUT_LEVEL - OUTPUT_STEP {
+                    self.output_level += OUTPUT_STEP;
+                }
+            } else if self.output_level >= OUTPUT_STEP {
+                self.output_level -= OUTPUT_STEP;
+            }
+        }
+        self.shift_register >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = BITS_PER_SAMPLE_BYTE;
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift_register = data;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    // Current output level, from 0 to 127
+    pub fn output(&self) -> u8 {
+        self.output_level
+    }
+}
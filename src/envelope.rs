@@ -0,0 +1,68 @@
+const ENVELOPE_LOOP: u8 = bit!(5);
+const ENVELOPE_CONSTANT_VOLUME: u8 = bit!(4);
+const ENVELOPE_VOLUME_MASK: u8 = mask!(4);
+
+const DECAY_START: u8 = 15;
+
+// Produces either a constant volume or a sawtooth decaying from 15 to 0,
+// shared by the pulse and noise channels
+#[derive(Debug, Clone, Copy)]
+pub struct Envelope {
+    start: bool,
+    looping: bool,
+    constant_volume: bool,
+    // the constant volume, and also the period of the decay divider
+    volume: u8,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Envelope {
+            start: false,
+            looping: false,
+            constant_volume: false,
+            volume: 0,
+            divider: 0,
+            decay: 0,
+        }
+    }
+
+    // Takes the low 6 bits of a channel's first register
+    pub fn write(&mut self, data: u8) {
+        self.looping = data & ENVELOPE_LOOP != 0;
+        self.constant_volume = data & ENVELOPE_CONSTANT_VOLUME != 0;
+        self.volume = data & ENVELOPE_VOLUME_MASK;
+    }
+
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    // Clocked every quarter frame
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = DECAY_START;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = DECAY_START;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use image::NesImage;
+use cartridge;
+use addressable;
+use addressable::{PpuAddressable, Address};
+use vram::NesVram;
+use mirror::Mirroring;
+use sunsoft_5b_audio::Sunsoft5bAudio;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x400;
+const NUM_CHR_BANKS: usize = 8;
+const NUM_PRG_BANKS: usize = 3;
+const CHR_RAM_SIZE: usize = 0x2000;
+
+const LOWER_ROM_ADDRESS: Address = 0x8000;
+const UPPER_ROM_ADDRESS: Address = 0xc000;
+
+// Writes to $8000-$9fff select a command, and writes to $a000-$bfff give
+// its parameter. The sound chip's ports are above them.
+const COMMAND_SELECT: Address = 0x8000;
+const COMMAND_PARAMETER: Address = 0xa000;
+const PORT_MASK: Address = 0xe000;
+
+const COMMAND_MASK: u8 = mask!(4);
+
+// Commands
+const CHR_SELECT_START: u8 = 0x0;
+const CHR_SELECT_END: u8 = 0x7;
+const RAM_BANK_SELECT: u8 = 0x8;
+const PRG_SELECT_START: u8 = 0x9;
+const PRG_SELECT_END: u8 = 0xb;
+const MIRRORING_SELECT: u8 = 0xc;
+const IRQ_CONTROL: u8 = 0xd;
+const IRQ_COUNTER_LOW: u8 = 0xe;
+const IRQ_COUNTER_HIGH: u8 = 0xf;
+
+const PRG_BANK_MASK: u8 = mask!(6);
+const RAM_BANK_SELECTS_RAM: u8 = bit!(6);
+const RAM_BANK_ENABLE: u8 = bit!(7);
+const MIRRORING_MASK: u8 = mask!(2);
+
+const IRQ_ENABLE: u8 = bit!(0);
+const IRQ_COUNTER_ENABLE: u8 = bit!(7);
+
+// Sunsoft's FME-7 (mapper 69): three switchable 8KB PRG banks followed by
+// the last 8KB, a switchable 8KB of ROM or RAM at $6000, eight 1KB CHR
+// banks, switchable mirroring, a CPU cycle IRQ counter and, on the 5B
+// variant, the Sunsoft 5B sound chip
+pub struct Fme7Cartridge {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    command: u8,
+    prg_banks: [usize; NUM_PRG_BANKS],
+    ram_bank: usize,
+    ram_selected: bool,
+    ram_enabled: bool,
+    chr_banks: [usize; NUM_CHR_BANKS],
+    // incremented on every CHR bank switch, for the PPU's tile cache
+    chr_generation: usize,
+    mirroring: Mirroring,
+    irq_enabled: bool,
+    irq_counter_enabled: bool,
+    irq_counter: u16,
+    irq_pending: bool,
+    audio: Sunsoft5bAudio,
+}
+
+impl Fme7Cartridge {
+    pub fn new(image: &NesImage) -> cartridge::Result<Self> {
+        if image.header.mapper_number != cartridge::FME7 {
+            return Err(cartridge::Error::IncorrectMapper);
+        }
+
+        if image.prg_rom.len() < PRG_BANK_SIZE || image.prg_rom.len() % PRG_BANK_SIZE != 0 {
+            return Err(cartridge::Error::InvalidRomSize);
+        }
+        if image.chr_rom.len() % CHR_BANK_SIZE != 0 {
+            return Err(cartridge::Error::InvalidChrRomSize);
+        }
+
+        let chr_is_ram = image.chr_rom.is_empty();
+        let chr = if chr_is_ram { vec![0; CHR_RAM_SIZE] } else { image.chr_rom.clone() };
+
+        Ok(Fme7Cartridge {
+            prg_rom: image.prg_rom.clone(),
+            prg_ram: vec![0; cartridge::RAM_BANK_SIZE],
+            chr: chr,
+            chr_is_ram: chr_is_ram,
+            command: 0,
+            prg_banks: [0, 1, 2],
+            ram_bank: 0,
+            ram_selected: false,
+            ram_enabled: false,
+            chr_banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            chr_generation: 0,
+            mirroring: Mirroring::Vertical,
+            irq_enabled: false,
+            irq_counter_enabled: false,
+            irq_counter: 0,
+            irq_pending: false,
+            audio: Sunsoft5bAudio::new(),
+        })
+    }
+
+    fn prg_bank_read(&self, bank: usize, offset: usize) -> u8 {
+        let num_banks = self.prg_rom.len() / PRG_BANK_SIZE;
+        self.prg_rom[(bank % num_banks) * PRG_BANK_SIZE + offset % PRG_BANK_SIZE]
+    }
+
+    fn prg_read(&self, address: Address) -> u8 {
+        let offset = (address - LOWER_ROM_ADDRESS) as usize;
+        let bank = match offset / PRG_BANK_SIZE {
+            slot @ 0...2 => self.prg_banks[slot],
+            _ => self.prg_rom.len() / PRG_BANK_SIZE - 1,
+        };
+        self.prg_bank_read(bank, offset)
+    }
+
+    fn chr_offset(&self, address: Address) -> usize {
+        let num_banks = self.chr.len() / CHR_BANK_SIZE;
+        let bank = self.chr_banks[address as usize / CHR_BANK_SIZE] % num_banks;
+        bank * CHR_BANK_SIZE + address as usize % CHR_BANK_SIZE
+    }
+
+    fn register_write(&mut self, address: Address, data: u8) {
+        match address & PORT_MASK {
+            COMMAND_SELECT => self.command = data & COMMAND_MASK,
+            COMMAND_PARAMETER => self.command_write(data),
+            port => self.audio.write(port, data),
+        }
+    }
+
+    fn command_write(&mut self, data: u8) {
+        match self.command {
+            CHR_SELECT_START...CHR_SELECT_END => {
+                self.chr_banks[self.command as usize] = data as usize;
+                self.chr_generation += 1;
+            }
+            RAM_BANK_SELECT => {
+                self.ram_bank = (data & PRG_BANK_MASK) as usize;
+                self.ram_selected = data & RAM_BANK_SELECTS_RAM != 0;
+                self.ram_enabled = data & RAM_BANK_ENABLE != 0;
+            }
+            PRG_SELECT_START...PRG_SELECT_END => {
+                let slot = (self.command - PRG_SELECT_START) as usize;
+                self.prg_banks[slot] = (data & PRG_BANK_MASK) as usize;
+            }
+            MIRRORING_SELECT => {
+                self.mirroring = match data & MIRRORING_MASK {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            }
+            IRQ_CONTROL => {
+                self.irq_enabled = data & IRQ_ENABLE != 0;
+                self.irq_counter_enabled = data & IRQ_COUNTER_ENABLE != 0;
+                self.irq_pending = false;
+            }
+            IRQ_COUNTER_LOW => self.irq_counter = (self.irq_counter & 0xff00) | data as u16,
+            IRQ_COUNTER_HIGH => self.irq_counter = (self.irq_counter & 0x00ff) | ((data as u16) << 8),
+            _ => {}
+        }
+    }
+}
+
+impl cartridge::Cartridge for Fme7Cartridge {
+    // The counter decrements every CPU cycle, raising an IRQ when it wraps
+    fn cpu_tick(&mut self) {
+        if self.irq_counter_enabled {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+            if self.irq_counter == 0xffff && self.irq_enabled {
+                self.irq_pending = true;
+            }
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+}
+
+impl cartridge::CartridgeAudio for Fme7Cartridge {
+    fn audio_tick(&mut self) {
+        self.audio.tick();
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+impl cartridge::CpuInterface for Fme7Cartridge {
+    // $6000-$7fff holds either a ROM bank or RAM, which reads as 0 while
+    // disabled
+    fn ram_read(&mut self, address: Address) -> addressable::Result<u8> {
+        if !self.ram_selected {
+            Ok(self.prg_bank_read(self.ram_bank, address as usize))
+        } else if self.ram_enabled {
+            Ok(self.prg_ram[address as usize % self.prg_ram.len()])
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn ram_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        if self.ram_selected && self.ram_enabled {
+            let len = self.prg_ram.len();
+            self.prg_ram[address as usize % len] = data;
+        }
+        Ok(())
+    }
+
+    fn lower_rom_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.prg_read(LOWER_ROM_ADDRESS + address))
+    }
+
+    fn lower_rom_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.register_write(LOWER_ROM_ADDRESS + address, data);
+        Ok(())
+    }
+
+    fn upper_rom_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.prg_read(UPPER_ROM_ADDRESS + address))
+    }
+
+    fn upper_rom_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.register_write(UPPER_ROM_ADDRESS + address, data);
+        Ok(())
+    }
+}
+
+impl cartridge::PpuInterface for Fme7Cartridge {
+    fn pattern_table_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.chr[self.chr_offset(address)])
+    }
+
+    fn pattern_table_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        if !self.chr_is_ram {
+            return Err(addressable::Error::IllegalWrite(address));
+        }
+        let offset = self.chr_offset(address);
+        self.chr[offset] = data;
+        Ok(())
+    }
+
+    fn name_table_read(&mut self, address: Address, ram: &mut NesVram) -> addressable::Result<u8> {
+        ram.ppu_read8(self.mirroring.mirror(address))
+    }
+
+    fn name_table_write(&mut self, address: Address, data: u8, ram: &mut NesVram) -> addressable::Result<()> {
+        ram.ppu_write8(self.mirroring.mirror(address), data)
+    }
+
+    fn pattern_table_generation(&self) -> usize {
+        self.chr_generation
+    }
+}
@@ -0,0 +1,135 @@
+use renderer::{Frame, Colour};
+
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        FrameBuffer {
+            width: width,
+            height: height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if width != self.width || height != self.height {
+            self.width = width;
+            self.height = height;
+            self.pixels = vec![0; width * height];
+        }
+    }
+
+    // Copies a rectangular region of this frame into `output`
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize, output: &mut FrameBuffer) {
+        output.resize(width, height);
+        for i in 0..height {
+            let src = (y + i) * self.width + x;
+            let dst = i * width;
+            output.pixels[dst..(dst + width)].copy_from_slice(&self.pixels[src..(src + width)]);
+        }
+    }
+
+    pub fn clear(&mut self, colour: Colour) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = colour;
+        }
+    }
+
+    pub fn pixels(&self) -> &[Colour] {
+        &self.pixels
+    }
+}
+
+impl Frame for FrameBuffer {
+    fn set_pixel(&mut self, x: usize, y: usize, colour: Colour) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = colour;
+        }
+    }
+
+    fn get_pixel(&self, x: usize, y: usize) -> Colour {
+        FrameBuffer::get_pixel(self, x, y)
+    }
+
+    fn set_row(&mut self, x: usize, y: usize, colours: &[Colour]) {
+        if y >= self.height || x >= self.width {
+            return;
+        }
+        let len = colours.len().min(self.width - x);
+        let start = y * self.width + x;
+        self.pixels[start..(start + len)].copy_from_slice(&colours[..len]);
+    }
+}
+
+pub const RGB_BYTES_PER_PIXEL: usize = 3;
+
+// Packed 24-bit RGB image, with rows stored contiguously
+#[derive(Clone)]
+pub struct RgbBuffer {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+impl RgbBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        RgbBuffer {
+            width: width,
+            height: height,
+            data: vec![0; width * height * RGB_BYTES_PER_PIXEL],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if width != self.width || height != self.height {
+            self.width = width;
+            self.height = height;
+            self.data = vec![0; width * height * RGB_BYTES_PER_PIXEL];
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        let offset = (y * self.width + x) * RGB_BYTES_PER_PIXEL;
+        (self.data[offset], self.data[offset + 1], self.data[offset + 2])
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, (r, g, b): (u8, u8, u8)) {
+        let offset = (y * self.width + x) * RGB_BYTES_PER_PIXEL;
+        self.data[offset] = r;
+        self.data[offset + 1] = g;
+        self.data[offset + 2] = b;
+    }
+
+    pub fn row(&self, y: usize) -> &[u8] {
+        let row_bytes = self.width * RGB_BYTES_PER_PIXEL;
+        &self.data[(y * row_bytes)..((y + 1) * row_bytes)]
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
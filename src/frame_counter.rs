@@ -0,0 +1,159 @@
+use image::TvSystem;
+
+const MODE_FIVE_STEP: u8 = bit!(7);
+const IRQ_INHIBIT: u8 = bit!(6);
+
+// CPU cycles after a reset of the sequencer at which each step occurs
+const NTSC_STEPS: [u64; 5] = [7457, 14913, 22371, 29829, 37281];
+const PAL_STEPS: [u64; 5] = [8313, 16627, 24939, 33253, 41565];
+
+// A write to $4017 takes effect 3 or 4 CPU cycles later, depending on
+// whether it lands on an APU cycle
+const WRITE_DELAY_ON_APU_CYCLE: u8 = 3;
+const WRITE_DELAY_BETWEEN_APU_CYCLES: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // clocks envelopes on 4 steps and length counters and sweeps on 2 of
+    // them, raising an IRQ at the end of the sequence
+    FourStep,
+    // the same but with a silent fourth step, and no IRQ
+    FiveStep,
+}
+
+// Which units should be clocked on a given cycle
+#[derive(Debug, Clone, Copy)]
+pub struct FrameClock {
+    // envelopes and the triangle's linear counter
+    pub quarter: bool,
+    // length counters and sweep units
+    pub half: bool,
+}
+
+impl FrameClock {
+    fn none() -> Self {
+        FrameClock {
+            quarter: false,
+            half: false,
+        }
+    }
+
+    fn quarter() -> Self {
+        FrameClock {
+            quarter: true,
+            half: false,
+        }
+    }
+
+    fn half() -> Self {
+        FrameClock {
+            quarter: true,
+            half: true,
+        }
+    }
+}
+
+pub struct FrameCounter {
+    steps: &'static [u64; 5],
+    mode: Mode,
+    irq_inhibit: bool,
+    irq: bool,
+    // CPU cycles since the sequencer was last reset
+    cycle: u64,
+    // a $4017 write and the number of cycles until it takes effect
+    pending_write: Option<(u8, u8)>,
+}
+
+impl FrameCounter {
+    pub fn new(tv_system: TvSystem) -> Self {
+        FrameCounter {
+            steps: match tv_system {
+                TvSystem::Ntsc => &NTSC_STEPS,
+                TvSystem::Pal => &PAL_STEPS,
+            },
+            mode: Mode::FourStep,
+            irq_inhibit: false,
+            irq: false,
+            cycle: 0,
+            pending_write: None,
+        }
+    }
+
+    pub fn write(&mut self, data: u8, on_apu_cycle: bool) {
+        // the inhibit flag takes effect immediately
+        self.irq_inhibit = data & IRQ_INHIBIT != 0;
+        if self.irq_inhibit {
+            self.irq = false;
+        }
+
+        let delay = if on_apu_cycle {
+            WRITE_DELAY_ON_APU_CYCLE
+        } else {
+            WRITE_DELAY_BETWEEN_APU_CYCLES
+        };
+        self.pending_write = Some((data, delay));
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    // Reading $4015 acknowledges the interrupt
+    pub fn clear_irq(&mut self) {
+        self.irq = false;
+    }
+
+    // Advances the sequencer by one CPU cycle
+    pub fn tick(&mut self) -> FrameClock {
+        if let Some((data, delay)) = self.pending_write {
+            if delay > 0 {
+                self.pending_write = Some((data, delay - 1));
+            } else {
+                self.pending_write = None;
+                self.cycle = 0;
+                self.mode = if data & MODE_FIVE_STEP != 0 { Mode::FiveStep } else { Mode::FourStep };
+
+                // entering 5-step mode clocks everything straight away
+                if self.mode == Mode::FiveStep {
+                    return FrameClock::half();
+                }
+                return FrameClock::none();
+            }
+        }
+
+        self.cycle += 1;
+        let steps = self.steps;
+
+        match self.mode {
+            Mode::FourStep => {
+                // the IRQ flag is raised on the last 3 cycles of the sequence
+                if self.cycle >= steps[3] - 1 && self.cycle <= steps[3] + 1 && !self.irq_inhibit {
+                    self.irq = true;
+                }
+
+                if self.cycle == steps[0] || self.cycle == steps[2] {
+                    FrameClock::quarter()
+                } else if self.cycle == steps[1] || self.cycle == steps[3] {
+                    FrameClock::half()
+                } else {
+                    if self.cycle == steps[3] + 1 {
+                        self.cycle = 0;
+                    }
+                    FrameClock::none()
+                }
+            }
+            Mode::FiveStep => {
+                if self.cycle == steps[0] || self.cycle == steps[2] {
+                    FrameClock::quarter()
+                } else if self.cycle == steps[1] || self.cycle == steps[4] {
+                    FrameClock::half()
+                } else {
+                    if self.cycle == steps[4] + 1 {
+                        self.cycle = 0;
+                    }
+                    FrameClock::none()
+                }
+            }
+        }
+    }
+}
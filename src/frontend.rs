@@ -1,4 +1,30 @@
+use cartridge;
+use cartridge::Cartridge;
+use nrom_cartridge::NromCartridge;
+use vrc6_cartridge::Vrc6Cartridge;
+use fme7_cartridge::Fme7Cartridge;
+use image::NesImage;
+
 pub trait Frontend {
     fn print_rom_dump(&mut self);
     fn run(&mut self);
 }
+
+// Creates a frontend once the concrete cartridge type is known
+pub trait FrontendBuilder {
+    fn build<C: Cartridge + 'static>(self, cartridge: C) -> Box<Frontend>;
+}
+
+pub fn init<B: FrontendBuilder>(image: &NesImage, builder: B) -> cartridge::Result<Box<Frontend>> {
+    match image.header.mapper_number {
+        cartridge::NROM => {
+            match try!(NromCartridge::new(image)) {
+                NromCartridge::HorizontalMirroring(cartridge) => Ok(builder.build(cartridge)),
+                NromCartridge::VerticalMirroring(cartridge) => Ok(builder.build(cartridge)),
+            }
+        }
+        cartridge::VRC6A | cartridge::VRC6B => Ok(builder.build(try!(Vrc6Cartridge::new(image)))),
+        cartridge::FME7 => Ok(builder.build(try!(Fme7Cartridge::new(image)))),
+        other => Err(cartridge::Error::UnknownMapper(other)),
+    }
+}
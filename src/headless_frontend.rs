@@ -0,0 +1,120 @@
+use frontend;
+use frontend::{Frontend, FrontendBuilder};
+use cartridge;
+use cartridge::Cartridge;
+use nes::NesWithCartridge;
+use image::{NesImage, TvSystem};
+use debug::NesDebug;
+use frame_buffer::FrameBuffer;
+use video::{VideoOptions, VideoPipeline};
+use screenshot;
+use wav::{WavWriter, ChannelRecording};
+use mixer::AudioOptions;
+use input::InputOptions;
+use ppu;
+
+// Runs the emulator for a fixed number of frames without opening a window
+pub struct HeadlessFrontend<C: Cartridge> {
+    nes: NesWithCartridge<C>,
+    frame: FrameBuffer,
+    video: VideoPipeline,
+    num_frames: usize,
+    screenshot_path: Option<String>,
+    audio: AudioOptions,
+}
+
+impl<C: Cartridge> HeadlessFrontend<C> {
+    pub fn new(cartridge: C,
+               tv_system: TvSystem,
+               options: VideoOptions,
+               num_frames: usize,
+               screenshot_path: Option<String>,
+               audio: AudioOptions,
+               input: InputOptions) -> Self {
+        let mut nes = NesWithCartridge::new(cartridge, tv_system);
+        audio.apply(nes.io.apu.mixer());
+        // nothing is pressed, but games still see what's plugged in
+        input.apply(&mut nes.io);
+
+        HeadlessFrontend {
+            nes: nes,
+            frame: FrameBuffer::new(ppu::DISPLAY_WIDTH, ppu::DISPLAY_HEIGHT),
+            video: VideoPipeline::new(options),
+            num_frames: num_frames,
+            screenshot_path: screenshot_path,
+            audio: audio,
+        }
+    }
+}
+
+impl<C: Cartridge> Frontend for HeadlessFrontend<C> {
+    fn print_rom_dump(&mut self) {
+        println!("{}", self.nes.dump_rom());
+    }
+
+    fn run(&mut self) {
+        self.nes.init().expect("Failed to initialise nes");
+
+        let sample_rate = self.nes.io.apu.mixer().sample_rate();
+        let mut recording = self.audio.record_path.as_ref().map(|path| {
+            WavWriter::create(path, sample_rate).expect("Failed to create audio recording")
+        });
+        let mut channel_recording = self.audio.channel_record_prefix.as_ref().map(|prefix| {
+            ChannelRecording::create(prefix, sample_rate).expect("Failed to create channel recordings")
+        });
+
+        for _ in 0..self.num_frames {
+            self.nes.emulate_frame(&mut self.frame).expect("Emulation failed");
+            if let Some(ref mut wav) = recording {
+                wav.write_samples(self.nes.io.apu.mixer().recorded_samples()).expect("Failed to record audio");
+            }
+            if let Some(ref mut channels) = channel_recording {
+                channels.write_frame(self.nes.io.apu.mixer()).expect("Failed to record channels");
+            }
+        }
+
+        if let Some(wav) = recording {
+            wav.finish().expect("Failed to record audio");
+        }
+        if let Some(channels) = channel_recording {
+            channels.finish().expect("Failed to record channels");
+        }
+
+        if let Some(ref path) = self.screenshot_path {
+            let image = self.video.process(&self.frame);
+            screenshot::write_ppm(image, path).expect("Failed to write screenshot");
+        }
+    }
+}
+
+struct HeadlessFrontendBuilder {
+    tv_system: TvSystem,
+    options: VideoOptions,
+    num_frames: usize,
+    screenshot_path: Option<String>,
+    audio: AudioOptions,
+    input: InputOptions,
+}
+
+impl FrontendBuilder for HeadlessFrontendBuilder {
+    fn build<C: Cartridge + 'static>(self, cartridge: C) -> Box<Frontend> {
+        Box::new(HeadlessFrontend::new(cartridge, self.tv_system, self.options, self.num_frames,
+                                         self.screenshot_path, self.audio, self.input))
+    }
+}
+
+pub fn init(image: &NesImage,
+            options: VideoOptions,
+            num_frames: usize,
+            screenshot_path: Option<String>,
+            audio: AudioOptions,
+            input: InputOptions) -> cartridge::Result<Box<Frontend>> {
+    frontend::init(image, HeadlessFrontendBuilder {
+        tv_system: image.header.tv_system,
+        options: options,
+        num_frames: num_frames,
+        screenshot_path: screenshot_path,
+        audio: audio,
+        input: input,
+    })
+}
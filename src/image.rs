@@ -36,7 +36,7 @@ pub enum VideoArrangement {
     FourScreenVram,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TvSystem {
     Ntsc,
     Pal,
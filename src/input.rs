@@ -0,0 +1,126 @@
+use io::Io;
+use controller::Controller;
+use multitap::{FourScore, FamicomMultitap};
+use zapper::Zapper;
+use vaus::{Vaus, VausModel};
+use renderer::Frame;
+
+pub const NUM_PORTS: usize = 2;
+
+// With a multitap, two players on each port
+pub const MAX_PLAYERS: usize = 4;
+
+// Anything that plugs into one of the console's controller ports
+pub trait InputDevice {
+    // Sets the strobe line, bit 0 of writes to $4016. Devices with shift
+    // registers reload them while it's high.
+    fn write_strobe(&mut self, strobe: bool);
+
+    // The bits the device drives when its port's register is read
+    fn read(&mut self) -> u8;
+
+    // Sets the held buttons of one of the device's pads, numbered from 0.
+    // Devices without pads ignore this.
+    fn set_buttons(&mut self, _pad: usize, _buttons: u8) {}
+
+    // Light guns look at each frame once it's drawn, then watch the beam,
+    // on the given visible scanline, or None in vblank
+    fn frame_rendered(&mut self, _frame: &Frame) {}
+
+    fn set_scanline(&mut self, _scanline: Option<usize>) {}
+
+    // Devices worked with the mouse are told where it's pointing on
+    // screen, and whether its button is held
+    fn set_pointer(&mut self, _position: Option<(usize, usize)>) {}
+
+    // The mouse's horizontal movement since the last frame, in pixels
+    fn move_pointer(&mut self, _dx: i32) {}
+
+    fn set_fire(&mut self, _pressed: bool) {}
+}
+
+// An empty port
+pub struct Unconnected;
+
+impl InputDevice for Unconnected {
+    fn write_strobe(&mut self, _: bool) {}
+
+    fn read(&mut self) -> u8 {
+        0
+    }
+}
+
+// What's plugged into the ports for more than two players
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Multitap {
+    None,
+    FourScore,
+    Famicom,
+}
+
+impl Multitap {
+    // The names used on the command line and in game configs
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Multitap::None),
+            "four_score" => Some(Multitap::FourScore),
+            "famicom" => Some(Multitap::Famicom),
+            _ => None,
+        }
+    }
+}
+
+// A device plugged into port 2 in place of a pad, or for the Famicom
+// Vaus, into the expansion port
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Device {
+    Controller,
+    Zapper,
+    Vaus,
+    FamicomVaus,
+}
+
+impl Device {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "controller" => Some(Device::Controller),
+            "zapper" => Some(Device::Zapper),
+            "vaus" => Some(Device::Vaus),
+            "famicom_vaus" => Some(Device::FamicomVaus),
+            _ => None,
+        }
+    }
+}
+
+pub struct InputOptions {
+    pub multitap: Multitap,
+    pub port2: Option<Device>,
+    // how far the Vaus's knob turns per pixel of mouse movement
+    pub paddle_sensitivity: f32,
+}
+
+impl InputOptions {
+    pub fn apply(&self, io: &mut Io) {
+        for port in 0..NUM_PORTS {
+            let device: Box<InputDevice> = match self.multitap {
+                Multitap::None => Box::new(Controller::new()),
+                Multitap::FourScore => Box::new(FourScore::new(port)),
+                Multitap::Famicom => Box::new(FamicomMultitap::new()),
+            };
+            io.connect(port, device);
+        }
+
+        let sensitivity = self.paddle_sensitivity;
+        match self.port2 {
+            Some(Device::Controller) => io.connect(1, Box::new(Controller::new())),
+            Some(Device::Zapper) => io.connect(1, Box::new(Zapper::new())),
+            Some(Device::Vaus) => io.connect(1, Box::new(Vaus::new(VausModel::Nes, 1, sensitivity))),
+            Some(Device::FamicomVaus) => {
+                for port in 0..NUM_PORTS {
+                    io.connect(port, Box::new(Vaus::new(VausModel::Famicom, port, sensitivity)));
+                }
+            }
+            None => {}
+        }
+    }
+}
@@ -1,4 +1,10 @@
 use addressable::{Addressable, Address, Result};
+use apu::Apu;
+use image::TvSystem;
+use controller::Controller;
+use input;
+use input::InputDevice;
+use renderer::Frame;
 
 const PULSE_START: Address = 0x00;
 const PULSE_END: Address = 0x07;
@@ -11,65 +17,104 @@ const DMC_END: Address = 0x13;
 pub const STATUS: Address = 0x15;
 pub const FRAME_COUNTER: Address = 0x17;
 
-pub const BUTTON_A: u8 = bit!(0);
-pub const BUTTON_B: u8 = bit!(1);
-pub const BUTTON_SELECT: u8 = bit!(2);
-pub const BUTTON_START: u8 = bit!(3);
-pub const BUTTON_UP: u8 = bit!(4);
-pub const BUTTON_DOWN: u8 = bit!(5);
-pub const BUTTON_LEFT: u8 = bit!(6);
-pub const BUTTON_RIGHT: u8 = bit!(7);
-
-struct IoRegisters {
-    joy1: u8,
-}
+const JOYPAD_1: Address = 0x16;
 
-impl IoRegisters {
-    fn new() -> Self {
-        IoRegisters {
-            joy1: 0,
-        }
-    }
-}
+const STROBE: u8 = bit!(0);
+
+// The controller ports drive bits 0-4 of the data bus. The rest keep the
+// last value on the bus, which for the usual absolute reads of $4016 and
+// $4017 is the high byte of the address.
+const PORT_DATA_MASK: u8 = mask!(5);
 
 pub struct Io {
-    registers: IoRegisters,
-    joy1: u8,
+    ports: [Box<InputDevice>; input::NUM_PORTS],
+    // the visible scanline being drawn, for light guns
+    scanline: Option<usize>,
+    // the last value on the CPU's data bus before the current read
+    open_bus: u8,
+    pub apu: Apu,
 }
 
 impl Io {
-    pub fn new() -> Self {
+    pub fn new(tv_system: TvSystem) -> Self {
         Io {
-            registers: IoRegisters::new(),
-            joy1: 0,
+            ports: [Box::new(Controller::new()), Box::new(Controller::new())],
+            scanline: None,
+            open_bus: 0,
+            apu: Apu::new(tv_system),
+        }
+    }
+
+    // Plugs a device into a port, numbered from 0
+    pub fn connect(&mut self, port: usize, device: Box<InputDevice>) {
+        self.ports[port] = device;
+    }
+
+    pub fn set_open_bus(&mut self, data: u8) {
+        self.open_bus = data;
+    }
+
+    pub fn set_scanline(&mut self, scanline: Option<usize>) {
+        self.scanline = scanline;
+    }
+
+    pub fn frame_rendered(&mut self, frame: &Frame) {
+        for port in self.ports.iter_mut() {
+            port.frame_rendered(frame);
+        }
+    }
+
+    // Points all the devices worked with the mouse
+    pub fn set_pointer(&mut self, position: Option<(usize, usize)>, dx: i32, fire: bool) {
+        for port in self.ports.iter_mut() {
+            port.set_pointer(position);
+            port.move_pointer(dx);
+            port.set_fire(fire);
         }
     }
 
-    pub fn joy1_press(&mut self, button: u8) {
-        self.joy1 |= button;
+    fn read_port(&mut self, port: usize) -> u8 {
+        self.ports[port].set_scanline(self.scanline);
+        (self.ports[port].read() & PORT_DATA_MASK) | (self.open_bus & !PORT_DATA_MASK)
+    }
+
+    // Players are numbered from 0, with the first two on pad 0 of each port
+    // and any others on the following pads
+    fn player_pad(player: usize) -> (usize, usize) {
+        (player % input::NUM_PORTS, player / input::NUM_PORTS)
+    }
+
+    pub fn set_buttons(&mut self, player: usize, buttons: u8) {
+        let (port, pad) = Self::player_pad(player);
+        self.ports[port].set_buttons(pad, buttons);
     }
 }
 
 impl Addressable for Io {
     fn read8(&mut self, address: Address) -> Result<u8> {
         match address {
-            0x16 => {
-                let data = self.registers.joy1 & bit!(0);
-                self.registers.joy1 >>= 1;
-                Ok(data)
-            }
+            JOYPAD_1 => Ok(self.read_port(0)),
+            // writes to $4017 go to the APU, but reads come from port 2
+            FRAME_COUNTER => Ok(self.read_port(1)),
+            STATUS => Ok(self.apu.read_status()),
             _ => Ok(0),
         }
     }
 
     fn write8(&mut self, address: Address, data: u8) -> Result<()> {
         match address {
-            0x16 => {
-                if data == 0 {
-                    self.registers.joy1 = self.joy1;
-                    self.joy1 = 0;
+            JOYPAD_1 => {
+                let strobe = data & STROBE != 0;
+                for port in self.ports.iter_mut() {
+                    port.write_strobe(strobe);
                 }
             }
+            PULSE_START...PULSE_END |
+            TRIANGLE_START...TRIANGLE_END |
+            NOISE_START...NOISE_END |
+            DMC_START...DMC_END |
+            STATUS |
+            FRAME_COUNTER => self.apu.write8(address, data),
             _ => {},
         }
 
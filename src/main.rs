@@ -2,10 +2,11 @@
 extern crate getopts;
 extern crate sdl2;
 
-use getopts::Options;
+use getopts::{Options, Matches};
 
 use std::env;
 use std::fs;
+use std::path::Path;
 
 #[macro_use]
 mod macros;
@@ -20,26 +21,221 @@ mod mirror;
 mod addressable;
 mod cartridge;
 mod nrom_cartridge;
+mod vrc6_cartridge;
+mod fme7_cartridge;
 mod cpu;
 mod ppu;
 mod io;
+mod controller;
+mod multitap;
+mod zapper;
+mod vaus;
+mod input;
+mod apu;
+mod pulse;
+mod triangle;
+mod noise;
+mod dmc;
+mod frame_counter;
+mod mixer;
+mod vrc6_audio;
+mod sunsoft_5b_audio;
+mod blip_buffer;
+mod envelope;
+mod length_counter;
 mod ppu_memory_layout;
+mod tile_cache;
 mod debug;
 mod instruction;
 mod palette;
+mod rgb_palette;
+mod ntsc;
+mod scaler;
+mod screenshot;
+mod video;
+mod frame_buffer;
+mod nametable_viewer;
+mod sprite_inspector;
 mod renderer;
 mod frontend;
 mod sdl_frontend;
+mod sdl_audio;
+mod sdl_gamepads;
+mod sdl_bindings;
+mod headless_frontend;
+mod config;
+mod wav;
+mod nsf;
+mod nsf_cartridge;
+mod nsf_player;
+mod sdl_nsf_frontend;
+
+const DEFAULT_SCALE: usize = 2;
+const DEFAULT_BINDINGS_PATH: &'static str = "bindings.toml";
+const DEFAULT_SCANLINE_BRIGHTNESS: f32 = 0.5;
 
 fn make_arg_parser() -> Options {
     let mut opts = Options::new();
 
     opts.optflag("d", "dump", "Print the contents of ROM");
     opts.optflag("h", "help", "Print help menu");
+    opts.optopt("", "scale", "Window size as a multiple of the NES display (default 2)", "N");
+    opts.optopt("f", "filter", "Upscaling filter: nearest (default), scale2x, scale3x or smooth2x", "FILTER");
+    opts.optflagopt("", "scanlines", "Darken alternate rows like a CRT, with optional brightness from 0 to 1", "BRIGHTNESS");
+    opts.optopt("", "headless", "Run for the given number of frames without opening a window", "FRAMES");
+    opts.optopt("", "screenshot", "Save the final frame of a headless run to a PPM file", "FILE");
+    opts.optopt("", "record-audio", "Record the emulator's audio to a WAV file", "FILE");
+    opts.optopt("", "multitap", "Adapter for players 3 and 4: none, four_score, famicom", "ADAPTER");
+    opts.optopt("", "port2", "Device in port 2: controller, zapper, vaus, famicom_vaus", "DEVICE");
+    opts.optopt("", "paddle-sensitivity", "How far the Vaus paddle turns per pixel of mouse movement", "AMOUNT");
+    opts.optopt("", "bindings", "Key and gamepad bindings file (default bindings.toml, if present)", "FILE");
+    opts.optopt("", "track", "The NSF track to play, numbered from 1", "N");
+    opts.optopt("", "record-channels", "Record each APU channel to its own WAV file, named PREFIX-CHANNEL.wav", "PREFIX");
+    opts.optopt("", "mute", "Channels to silence: pulse1, pulse2, triangle, noise, dmc, expansion", "CHANNEL,...");
+    opts.optopt("", "solo", "The only channel to play", "CHANNEL");
+    opts.optopt("n", "ntsc", "Apply an NTSC composite video filter: composite, svideo, rgb or monochrome", "PRESET");
+    opts.optopt("", "overscan", "Pixels to crop from each edge (default depends on region)", "TOP,BOTTOM,LEFT,RIGHT");
+    opts.optflag("", "aspect", "Correct the picture to the TV's pixel aspect ratio");
+    opts.optflag("", "no-aspect", "Display square pixels");
+    opts.optopt("p", "palette", "Colour palette to use: 2c02 (default), 2c03, fceux, or a .pal file", "PALETTE");
 
     opts
 }
 
+// Reads the options shared by games and NSFs, printing the problem with any
+// that are invalid
+fn parse_audio_options(matches: &Matches) -> Option<mixer::AudioOptions> {
+    let mut muted = Vec::new();
+    if let Some(s) = matches.opt_str("mute") {
+        for name in s.split(',') {
+            match mixer::Channel::parse(name) {
+                Some(channel) => muted.push(channel),
+                None => {
+                    println!("Unknown channel: {}", name);
+                    return None;
+                }
+            }
+        }
+    }
+
+    let solo = match matches.opt_str("solo") {
+        Some(name) => match mixer::Channel::parse(&name) {
+            Some(channel) => Some(channel),
+            None => {
+                println!("Unknown channel: {}", name);
+                return None;
+            }
+        },
+        None => None,
+    };
+
+    Some(mixer::AudioOptions {
+        record_path: matches.opt_str("record-audio"),
+        channel_record_prefix: matches.opt_str("record-channels"),
+        muted: muted,
+        solo: solo,
+    })
+}
+
+// Loads the bindings file given on the command line, or the default one if
+// it exists, printing any problem with it
+fn load_bindings(matches: &Matches) -> Option<sdl_bindings::Bindings> {
+    let mut bindings = sdl_bindings::Bindings::new();
+
+    let path = matches.opt_str("bindings");
+    let file = match path {
+        Some(ref path) => match fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                println!("{}: {}", path, e);
+                return None;
+            }
+        },
+        None => match fs::File::open(DEFAULT_BINDINGS_PATH) {
+            Ok(f) => f,
+            Err(_) => return Some(bindings),
+        },
+    };
+
+    let config = match config::Config::parse_file(file) {
+        Ok(c) => c,
+        Err(e) => {
+            println!("Invalid bindings: {:?}", e);
+            return None;
+        }
+    };
+
+    if let Err(e) = bindings.apply_config(&config) {
+        println!("Invalid bindings: {}", e);
+        return None;
+    }
+
+    Some(bindings)
+}
+
+fn is_nsf(filename: &str) -> bool {
+    match Path::new(filename).extension().and_then(|e| e.to_str()) {
+        Some(extension) => {
+            let extension = extension.to_lowercase();
+            extension == "nsf" || extension == "nsfe"
+        }
+        None => false,
+    }
+}
+
+// Plays an NSF, or with --headless renders it to WAV files
+fn run_nsf(file: fs::File, matches: &Matches) {
+    let image = match nsf::parse_file(file) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("{:?}", e);
+            return;
+        }
+    };
+
+    let audio = match parse_audio_options(matches) {
+        Some(a) => a,
+        None => return,
+    };
+
+    let track = match matches.opt_str("track").map(|s| s.parse::<u8>()) {
+        Some(Ok(track)) => track,
+        Some(Err(_)) => {
+            println!("Invalid track");
+            return;
+        }
+        None => image.header.starting_song,
+    };
+    if track < 1 || track > image.header.num_songs {
+        println!("Track must be from 1 to {}", image.header.num_songs);
+        return;
+    }
+
+    let player = match nsf_player::NsfPlayer::new(image, audio, track) {
+        Ok(p) => p,
+        Err(e) => {
+            println!("{:?}", e);
+            return;
+        }
+    };
+
+    let mut frontend: Box<frontend::Frontend> = match matches.opt_str("headless").map(|s| s.parse::<usize>()) {
+        Some(Ok(num_frames)) => Box::new(nsf_player::HeadlessNsfFrontend::new(player, num_frames)),
+        Some(Err(_)) => {
+            println!("Invalid number of frames");
+            return;
+        }
+        None => Box::new(sdl_nsf_frontend::SdlNsfFrontend::new(player)),
+    };
+
+    frontend.print_rom_dump();
+    if matches.opt_present("d") {
+        return;
+    }
+
+    frontend.run();
+}
+
 fn print_usage(program: &str, parser: Options) {
     let brief = format!("Usage: {} FILE", program);
     println!("{}", parser.usage(&brief));
@@ -66,7 +262,7 @@ fn main() {
         matches.free[0].clone()
     };
 
-    let file = match fs::File::open(filename) {
+    let file = match fs::File::open(&filename) {
         Ok(f) => f,
         Err(e) => {
             println!("{}", e.to_string());
@@ -74,6 +270,11 @@ fn main() {
         }
     };
 
+    if is_nsf(&filename) {
+        run_nsf(file, &matches);
+        return;
+    }
+
     let image = match ines::parse_file(file) {
         Ok(i) => i,
         Err(e) => {
@@ -82,7 +283,198 @@ fn main() {
         }
     };
 
-    let mut frontend = match sdl_frontend::init(&image) {
+    let palette_name = matches.opt_str("p").unwrap_or(rgb_palette::DEFAULT.to_string());
+    let palette = match rgb_palette::RgbPalette::builtin(&palette_name) {
+        Some(p) => p,
+        None => {
+            let palette_file = match fs::File::open(&palette_name) {
+                Ok(f) => f,
+                Err(e) => {
+                    println!("{}", e.to_string());
+                    return;
+                }
+            };
+            match rgb_palette::RgbPalette::parse_file(palette_file) {
+                Ok(p) => p,
+                Err(e) => {
+                    println!("{:?}", e);
+                    return;
+                }
+            }
+        }
+    };
+
+    let ntsc = match matches.opt_str("n") {
+        Some(preset) => {
+            match ntsc::NtscSettings::preset(&preset) {
+                Some(settings) => Some(settings),
+                None => {
+                    println!("Unknown NTSC preset: {}", preset);
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let scale = match matches.opt_str("scale").map(|s| s.parse::<usize>()) {
+        Some(Ok(scale)) if scale > 0 => scale,
+        Some(_) => {
+            println!("Invalid scale");
+            return;
+        }
+        None => DEFAULT_SCALE,
+    };
+
+    let mut post_process = scaler::PostProcess::new();
+
+    let filter_name = matches.opt_str("f").unwrap_or("nearest".to_string());
+    post_process.scaler = match scaler::Scaler::from_name(&filter_name, scale) {
+        Some(s) => s,
+        None => {
+            println!("Unknown filter: {}", filter_name);
+            return;
+        }
+    };
+
+    if matches.opt_present("scanlines") {
+        post_process.scanlines = match matches.opt_str("scanlines").map(|s| s.parse::<f32>()) {
+            Some(Ok(brightness)) => Some(brightness.max(0.0).min(1.0)),
+            Some(Err(_)) => {
+                println!("Invalid scanline brightness");
+                return;
+            }
+            None => Some(DEFAULT_SCANLINE_BRIGHTNESS),
+        };
+        if post_process.scaler.factor() < 2 {
+            println!("Scanlines need a scale of at least 2");
+            return;
+        }
+    }
+
+    // per-game settings live next to the ROM, e.g. game.nes -> game.toml
+    let game_config_path = Path::new(&filename).with_extension("toml");
+    let game_config = if game_config_path.exists() {
+        let config_file = match fs::File::open(&game_config_path) {
+            Ok(f) => f,
+            Err(e) => {
+                println!("{}", e.to_string());
+                return;
+            }
+        };
+        match config::Config::parse_file(config_file) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("{:?}", e);
+                return;
+            }
+        }
+    } else {
+        config::Config::new()
+    };
+
+    let mut overscan = video::Overscan::for_tv_system(&image.header.tv_system);
+    overscan.apply_config(&game_config, &image.header.tv_system);
+    if let Some(s) = matches.opt_str("overscan") {
+        overscan = match video::Overscan::parse(&s) {
+            Some(o) => o,
+            None => {
+                println!("Invalid overscan: {}", s);
+                return;
+            }
+        };
+    }
+    if !overscan.is_valid() {
+        println!("Overscan crops the whole picture");
+        return;
+    }
+
+    let mut aspect_correction = video::config_bool(&game_config, &image.header.tv_system, "aspect_correction")
+        .unwrap_or(false);
+    if matches.opt_present("aspect") {
+        aspect_correction = true;
+    }
+    if matches.opt_present("no-aspect") {
+        aspect_correction = false;
+    }
+
+    let pixel_aspect = if aspect_correction {
+        Some(video::pixel_aspect(&image.header.tv_system))
+    } else {
+        None
+    };
+
+    let options = video::VideoOptions {
+        palette: palette,
+        ntsc: ntsc,
+        post_process: post_process,
+        scale: scale,
+        overscan: overscan,
+        pixel_aspect: pixel_aspect,
+    };
+
+    let audio = match parse_audio_options(&matches) {
+        Some(a) => a,
+        None => return,
+    };
+
+    let multitap_name = matches.opt_str("multitap")
+        .or(game_config.get_str("input", "multitap").map(|s| s.to_string()));
+    let multitap = match multitap_name {
+        Some(name) => match input::Multitap::parse(&name) {
+            Some(m) => m,
+            None => {
+                println!("Unknown multitap: {}", name);
+                return;
+            }
+        },
+        None => input::Multitap::None,
+    };
+    let port2_name = matches.opt_str("port2")
+        .or(game_config.get_str("input", "port2").map(|s| s.to_string()));
+    let port2 = match port2_name {
+        Some(name) => match input::Device::parse(&name) {
+            Some(d) => Some(d),
+            None => {
+                println!("Unknown device: {}", name);
+                return;
+            }
+        },
+        None => None,
+    };
+    let paddle_sensitivity = match matches.opt_str("paddle-sensitivity").map(|s| s.parse::<f32>()) {
+        Some(Ok(s)) => s,
+        Some(Err(_)) => {
+            println!("Invalid paddle sensitivity");
+            return;
+        }
+        None => game_config.get_float("input", "paddle_sensitivity")
+            .map_or(vaus::DEFAULT_SENSITIVITY, |s| s as f32),
+    };
+    let input = input::InputOptions {
+        multitap: multitap,
+        port2: port2,
+        paddle_sensitivity: paddle_sensitivity,
+    };
+
+    let frontend = match matches.opt_str("headless").map(|s| s.parse::<usize>()) {
+        Some(Ok(num_frames)) => headless_frontend::init(&image, options, num_frames,
+                                                               matches.opt_str("screenshot"),
+                                                               audio, input),
+        Some(Err(_)) => {
+            println!("Invalid number of frames");
+            return;
+        }
+        None => {
+            let bindings = match load_bindings(&matches) {
+                Some(b) => b,
+                None => return,
+            };
+            sdl_frontend::init(&image, options, audio, input, bindings)
+        }
+    };
+
+    let mut frontend = match frontend {
         Ok(f) => f,
         Err(e) => {
             println!("{:?}", e);
@@ -1,7 +1,7 @@
 use ram::NesRam;
 use vram::NesVram;
 use cartridge::Cartridge;
-use addressable::{Addressable, Address, AddressDiff, Result, Error};
+use addressable::{Addressable, Address, AddressDiff, Result};
 use ppu::Ppu;
 use ppu_memory_layout::PpuMemoryLayout;
 use io::Io;
@@ -39,6 +39,8 @@ pub struct MemoryLayout<'a, C: 'a + Cartridge> {
     ram: &'a mut NesRam,
     vram: &'a mut NesVram,
     palette: &'a mut Palette,
+    // the last value read or written, which undriven bits of reads keep
+    data_bus: &'a mut u8,
 }
 
 impl<'a, C: 'a + Cartridge> MemoryLayout<'a, C> {
@@ -47,7 +49,8 @@ impl<'a, C: 'a + Cartridge> MemoryLayout<'a, C> {
                io: &'a mut Io,
                ram: &'a mut NesRam,
                vram: &'a mut NesVram,
-               palette: &'a mut Palette)
+               palette: &'a mut Palette,
+               data_bus: &'a mut u8)
                -> Self {
 
         MemoryLayout {
@@ -57,6 +60,7 @@ impl<'a, C: 'a + Cartridge> MemoryLayout<'a, C> {
             ram: ram,
             vram: vram,
             palette: palette,
+            data_bus: data_bus,
         }
     }
 
@@ -79,10 +83,8 @@ impl<'a, C: 'a + Cartridge> MemoryLayout<'a, C> {
     pub fn ppu_memory_layout(&mut self) -> PpuMemoryLayout<C> {
         PpuMemoryLayout::new(self.cartridge, self.vram, self.palette)
     }
-}
 
-impl<'a, C: 'a + Cartridge> Addressable for MemoryLayout<'a, C> {
-    fn read8(&mut self, address: Address) -> Result<u8> {
+    fn read_device(&mut self, address: Address) -> Result<u8> {
         match address {
             RAM_START...RAM_MIRROR_END => self.ram.read8(address % RAM_SIZE),
             PPU_REGISTER_START...PPU_REGISTER_MIRROR_END => {
@@ -90,13 +92,32 @@ impl<'a, C: 'a + Cartridge> Addressable for MemoryLayout<'a, C> {
                 self.ppu.read8((address - PPU_REGISTER_START) % PPU_REGISTER_SIZE, ppu_memory)
             }
             IO_REGISTER_START...IO_REGISTER_END => {
+                self.io.set_open_bus(*self.data_bus);
                 self.io.read8(address - IO_REGISTER_START)
             }
+            EXPANSION_ROM_START...EXPANSION_ROM_END => {
+                let data = try!(self.cartridge.expansion_read(address));
+                Ok(data.unwrap_or(*self.data_bus))
+            }
             CARTRIDGE_START...CARTRIDGE_END => self.cartridge.read8(address - CARTRIDGE_START),
-            _ => Err(Error::UnimplementedRead(address)),
         }
     }
+}
+
+impl<'a, C: 'a + Cartridge> Addressable for MemoryLayout<'a, C> {
+    fn read8(&mut self, address: Address) -> Result<u8> {
+        let data = try!(self.read_device(address));
+        *self.data_bus = data;
+        Ok(data)
+    }
+
+    // Debugger reads leave the bus alone
+    fn read8_pure(&mut self, address: Address) -> Result<u8> {
+        self.read_device(address)
+    }
+
     fn write8(&mut self, address: Address, data: u8) -> Result<()> {
+        *self.data_bus = data;
         if address == PPU_OAM_DMA {
             return self.ppu_oam_dma(data);
         }
@@ -109,10 +130,10 @@ impl<'a, C: 'a + Cartridge> Addressable for MemoryLayout<'a, C> {
             IO_REGISTER_START...IO_REGISTER_END => {
                 self.io.write8(address - IO_REGISTER_START, data)
             }
+            EXPANSION_ROM_START...EXPANSION_ROM_END => self.cartridge.expansion_write(address, data),
             CARTRIDGE_START...CARTRIDGE_END => {
                 self.cartridge.write8(address - CARTRIDGE_START, data)
             }
-            _ => Err(Error::UnimplementedWrite(address)),
         }
     }
 }
@@ -1,6 +1,7 @@
 use addressable::Address;
 
 pub const MIRROR_SIZE: Address = 0x1000;
+const NAME_TABLE_SIZE: Address = 0x400;
 
 pub trait Mirror {
     fn mirror(address: Address) -> Address;
@@ -14,7 +15,7 @@ pub struct VerticalMirror;
 
 impl Mirror for HorizontalMirror {
     fn mirror(address: Address) -> Address {
-        address / 2
+        (address / (MIRROR_SIZE / 2)) * NAME_TABLE_SIZE + address % NAME_TABLE_SIZE
     }
 }
 
@@ -23,3 +24,23 @@ impl Mirror for VerticalMirror {
         address % (MIRROR_SIZE / 2)
     }
 }
+
+// For mappers that switch the arrangement at run time
+#[derive(Debug, Clone, Copy)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    SingleScreenLower,
+    SingleScreenUpper,
+}
+
+impl Mirroring {
+    pub fn mirror(&self, address: Address) -> Address {
+        match *self {
+            Mirroring::Horizontal => HorizontalMirror::mirror(address),
+            Mirroring::Vertical => VerticalMirror::mirror(address),
+            Mirroring::SingleScreenLower => address % NAME_TABLE_SIZE,
+            Mirroring::SingleScreenUpper => NAME_TABLE_SIZE + address % NAME_TABLE_SIZE,
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::f32::consts::PI;
+
+use apu::ApuOutput;
+use blip_buffer::BlipBuffer;
+use image::TvSystem;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+const NTSC_CPU_CLOCK_RATE: f64 = 1789773.0;
+const PAL_CPU_CLOCK_RATE: f64 = 1662607.0;
+
+// The outputs of the pulse channels are summed before their DAC, as are
+// those of the triangle, noise and DMC channels with these weights.
+const PULSE_TABLE_SIZE: usize = 31;
+const TND_TABLE_SIZE: usize = 203;
+const TND_TRIANGLE_WEIGHT: usize = 3;
+const TND_NOISE_WEIGHT: usize = 2;
+
+// The filters between the APU and the audio output
+const HIGH_PASS_1_CUTOFF: f32 = 90.0;
+const HIGH_PASS_2_CUTOFF: f32 = 440.0;
+const LOW_PASS_CUTOFF: f32 = 14000.0;
+
+pub fn cpu_clock_rate(tv_system: TvSystem) -> f64 {
+    match tv_system {
+        TvSystem::Ntsc => NTSC_CPU_CLOCK_RATE,
+        TvSystem::Pal => PAL_CPU_CLOCK_RATE,
+    }
+}
+
+// Converts a sample to 16 bits, clipping it to the range -1 to 1
+pub fn to_i16(sample: f32) -> i16 {
+    (sample.max(-1.0).min(1.0) * i16::max_value() as f32) as i16
+}
+
+struct HighPassFilter {
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl HighPassFilter {
+    fn new(cutoff: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        HighPassFilter {
+            alpha: rc / (rc + dt),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    fn apply(&mut self, input: f32) -> f32 {
+        let output = self.alpha * (self.previous_output + input - self.previous_input);
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+struct LowPassFilter {
+    alpha: f32,
+    previous_output: f32,
+}
+
+impl LowPassFilter {
+    fn new(cutoff: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        LowPassFilter {
+            alpha: dt / (rc + dt),
+            previous_output: 0.0,
+        }
+    }
+
+    fn apply(&mut self, input: f32) -> f32 {
+        self.previous_output += self.alpha * (input - self.previous_output);
+        self.previous_output
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+    // all of the cartridge's sound channels together
+    Expansion,
+}
+
+pub const NUM_CHANNELS: usize = 6;
+
+pub const CHANNELS: [Channel; NUM_CHANNELS] = [
+    Channel::Pulse1,
+    Channel::Pulse2,
+    Channel::Triangle,
+    Channel::Noise,
+    Channel::Dmc,
+    Channel::Expansion,
+];
+
+impl Channel {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Channel::Pulse1 => "pulse1",
+            Channel::Pulse2 => "pulse2",
+            Channel::Triangle => "triangle",
+            Channel::Noise => "noise",
+            Channel::Dmc => "dmc",
+            Channel::Expansion => "expansion",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Channel> {
+        CHANNELS.iter().cloned().find(|c| c.name() == name)
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+// How the frontends set up the mixer, and what they record
+pub struct AudioOptions {
+    pub record_path: Option<String>,
+    pub channel_record_prefix: Option<String>,
+    pub muted: Vec<Channel>,
+    pub solo: Option<Channel>,
+}
+
+impl AudioOptions {
+    pub fn apply(&self, mixer: &mut Mixer) {
+        for &channel in &self.muted {
+            mixer.set_muted(channel, true);
+        }
+        mixer.set_solo(self.solo);
+        if self.record_path.is_some() {
+            mixer.enable_recording_track();
+        }
+        if self.channel_record_prefix.is_some() {
+            mixer.enable_channel_tracks();
+        }
+    }
+}
+
+// A band-limited, filtered stream of samples from a level that changes at
+// arbitrary CPU cycles
+struct Track {
+    blip: BlipBuffer,
+    high_pass_1: HighPassFilter,
+    high_pass_2: HighPassFilter,
+    low_pass: LowPassFilter,
+    level: f32,
+    samples: Vec<f32>,
+}
+
+impl Track {
+    fn new(clock_rate: f64, sample_rate: u32) -> Self {
+        let rate = sample_rate as f32;
+        Track {
+            blip: BlipBuffer::new(clock_rate, sample_rate as f64),
+            high_pass_1: HighPassFilter::new(HIGH_PASS_1_CUTOFF, rate),
+            high_pass_2: HighPassFilter::new(HIGH_PASS_2_CUTOFF, rate),
+            low_pass: LowPassFilter::new(LOW_PASS_CUTOFF, rate),
+            level: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    fn set_sample_rate(&mut self, clock_rate: f64, sample_rate: u32) {
+        let rate = sample_rate as f32;
+        self.blip.set_rates(clock_rate, sample_rate as f64);
+        self.high_pass_1 = HighPassFilter::new(HIGH_PASS_1_CUTOFF, rate);
+        self.high_pass_2 = HighPassFilter::new(HIGH_PASS_2_CUTOFF, rate);
+        self.low_pass = LowPassFilter::new(LOW_PASS_CUTOFF, rate);
+    }
+
+    fn set_level(&mut self, clock: u64, level: f32) {
+        if level != self.level {
+            self.blip.add_delta(clock, level - self.level);
+            self.level = level;
+        }
+    }
+
+    fn end_frame(&mut self, clocks: u64) {
+        self.samples.clear();
+        self.blip.end_frame(clocks, &mut self.samples);
+
+        for sample in self.samples.iter_mut() {
+            let filtered = self.high_pass_1.apply(*sample);
+            let filtered = self.high_pass_2.apply(filtered);
+            *sample = self.low_pass.apply(filtered);
+        }
+    }
+}
+
+// Mixes the APU channels into audio samples at the host's sample rate
+pub struct Mixer {
+    pulse_table: [f32; PULSE_TABLE_SIZE],
+    tnd_table: [f32; TND_TABLE_SIZE],
+    clock_rate: f64,
+    sample_rate: u32,
+    output: Track,
+    // The output at the nominal rate, unaffected by `adjust_rate`, for
+    // recordings whose headers give that rate
+    recording_track: Option<Track>,
+    muted: [bool; NUM_CHANNELS],
+    solo: Option<Channel>,
+    // Each channel on its own, as it would sound soloed, when enabled
+    channel_tracks: Option<Vec<Track>>,
+    frame_start_cycle: u64,
+}
+
+impl Mixer {
+    pub fn new(tv_system: TvSystem, sample_rate: u32) -> Self {
+        let mut pulse_table = [0.0; PULSE_TABLE_SIZE];
+        for (n, entry) in pulse_table.iter_mut().enumerate().skip(1) {
+            *entry = 95.52 / (8128.0 / n as f32 + 100.0);
+        }
+
+        let mut tnd_table = [0.0; TND_TABLE_SIZE];
+        for (n, entry) in tnd_table.iter_mut().enumerate().skip(1) {
+            *entry = 163.67 / (24329.0 / n as f32 + 100.0);
+        }
+
+        let clock_rate = cpu_clock_rate(tv_system);
+
+        Mixer {
+            pulse_table: pulse_table,
+            tnd_table: tnd_table,
+            clock_rate: clock_rate,
+            sample_rate: sample_rate,
+            output: Track::new(clock_rate, sample_rate),
+            recording_track: None,
+            muted: [false; NUM_CHANNELS],
+            solo: None,
+            channel_tracks: None,
+            frame_start_cycle: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Changes the output rate, e.g. to match an audio device
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.output.set_sample_rate(self.clock_rate, sample_rate);
+        if let Some(ref mut track) = self.recording_track {
+            track.set_sample_rate(self.clock_rate, sample_rate);
+        }
+        if let Some(ref mut tracks) = self.channel_tracks {
+            for track in tracks.iter_mut() {
+                track.set_sample_rate(self.clock_rate, sample_rate);
+            }
+        }
+    }
+
+    // Produces slightly more or fewer samples per frame than the nominal
+    // rate, so a frontend can keep its audio queue from draining or growing.
+    // The recording and channel tracks keep the nominal rate so captures
+    // stay in step.
+    pub fn adjust_rate(&mut self, ratio: f64) {
+        self.output.blip.set_rates(self.clock_rate, self.sample_rate as f64 * ratio);
+    }
+
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        self.muted[channel.index()] = muted;
+    }
+
+    pub fn toggle_muted(&mut self, channel: Channel) {
+        self.muted[channel.index()] = !self.muted[channel.index()];
+    }
+
+    pub fn is_muted(&self, channel: Channel) -> bool {
+        self.muted[channel.index()]
+    }
+
+    // Silences every channel but the given one, regardless of muting
+    pub fn set_solo(&mut self, channel: Option<Channel>) {
+        self.solo = channel;
+    }
+
+    pub fn solo(&self) -> Option<Channel> {
+        self.solo
+    }
+
+    pub fn is_audible(&self, channel: Channel) -> bool {
+        match self.solo {
+            Some(solo) => solo == channel,
+            None => !self.is_muted(channel),
+        }
+    }
+
+    // Starts producing the output again at the nominal rate, for recording
+    pub fn enable_recording_track(&mut self) {
+        if self.recording_track.is_none() {
+            self.recording_track = Some(Track::new(self.clock_rate, self.sample_rate));
+        }
+    }
+
+    // Starts producing a separate track for each channel
+    pub fn enable_channel_tracks(&mut self) {
+        if self.channel_tracks.is_none() {
+            let (clock_rate, sample_rate) = (self.clock_rate, self.sample_rate);
+            self.channel_tracks = Some(CHANNELS.iter().map(|_| Track::new(clock_rate, sample_rate)).collect());
+        }
+    }
+
+    // Mixes the channels' levels at the given CPU cycle
+    pub fn update(&mut self, cycle: u64, output: &ApuOutput) {
+        let clock = cycle - self.frame_start_cycle;
+
+        let level = self.mix(output, |channel| self.is_audible(channel));
+        self.output.set_level(clock, level);
+        if let Some(ref mut track) = self.recording_track {
+            track.set_level(clock, level);
+        }
+
+        if self.channel_tracks.is_some() {
+            let mut track_levels = [0.0; NUM_CHANNELS];
+            for (&channel, track_level) in CHANNELS.iter().zip(track_levels.iter_mut()) {
+                *track_level = self.mix(output, |c| c == channel);
+            }
+            if let Some(ref mut tracks) = self.channel_tracks {
+                for (track, &level) in tracks.iter_mut().zip(track_levels.iter()) {
+                    track.set_level(clock, level);
+                }
+            }
+        }
+    }
+
+    // The combined level of the included channels
+    fn mix<F: Fn(Channel) -> bool>(&self, output: &ApuOutput, included: F) -> f32 {
+        let level = |channel, level| if included(channel) { level as usize } else { 0 };
+
+        let pulse = self.pulse_table[level(Channel::Pulse1, output.pulse_1) +
+                                     level(Channel::Pulse2, output.pulse_2)];
+        let tnd_index = level(Channel::Triangle, output.triangle) * TND_TRIANGLE_WEIGHT +
+                        level(Channel::Noise, output.noise) * TND_NOISE_WEIGHT +
+                        level(Channel::Dmc, output.dmc);
+        let expansion = if included(Channel::Expansion) { output.expansion } else { 0.0 };
+
+        pulse + self.tnd_table[tnd_index] + expansion
+    }
+
+    // Completes the samples up to the given CPU cycle, replacing the
+    // previous frame's samples
+    pub fn end_frame(&mut self, cycle: u64) {
+        let clocks = cycle - self.frame_start_cycle;
+        self.output.end_frame(clocks);
+        if let Some(ref mut track) = self.recording_track {
+            track.end_frame(clocks);
+        }
+        if let Some(ref mut tracks) = self.channel_tracks {
+            for track in tracks.iter_mut() {
+                track.end_frame(clocks);
+            }
+        }
+        self.frame_start_cycle = cycle;
+    }
+
+    // The samples of the last frame, from -1 to 1
+    pub fn samples(&self) -> &[f32] {
+        &self.output.samples
+    }
+
+    // The last frame's samples to record: at the nominal rate if the
+    // recording track is enabled
+    pub fn recorded_samples(&self) -> &[f32] {
+        match self.recording_track {
+            Some(ref track) => &track.samples,
+            None => &self.output.samples,
+        }
+    }
+
+    // The last frame's samples of one channel, if channel tracks are enabled
+    pub fn channel_samples(&self, channel: Channel) -> Option<&[f32]> {
+        self.channel_tracks.as_ref().map(|tracks| &tracks[channel.index()].samples[..])
+    }
+}
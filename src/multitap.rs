@@ -0,0 +1,92 @@
+use input::InputDevice;
+use controller::Controller;
+
+// The Four Score sends each port's two pads one after the other, then a
+// signature which tells games it's there: a one on the 4th bit for port 1,
+// and on the 3rd for port 2
+const FOUR_SCORE_SIGNATURES: [u8; 2] = [bit!(3), bit!(2)];
+const FOUR_SCORE_NUM_BITS: usize = 24;
+
+// Half of an NES Four Score, the part plugged into one port. Players 1 and
+// 3 are read from port 1, and 2 and 4 from port 2.
+pub struct FourScore {
+    buttons: [u8; 2],
+    signature: u8,
+    shift_register: u32,
+    strobe: bool,
+}
+
+impl FourScore {
+    pub fn new(port: usize) -> Self {
+        FourScore {
+            buttons: [0; 2],
+            signature: FOUR_SCORE_SIGNATURES[port],
+            shift_register: 0,
+            strobe: false,
+        }
+    }
+
+    fn reload(&mut self) {
+        self.shift_register = self.buttons[0] as u32 |
+                              (self.buttons[1] as u32) << 8 |
+                              (self.signature as u32) << 16;
+    }
+}
+
+impl InputDevice for FourScore {
+    fn write_strobe(&mut self, strobe: bool) {
+        if strobe || self.strobe {
+            self.reload();
+        }
+        self.strobe = strobe;
+    }
+
+    fn read(&mut self) -> u8 {
+        if self.strobe {
+            return self.buttons[0] & bit!(0);
+        }
+        let data = (self.shift_register & 1) as u8;
+        // ones follow the signature, as with a single pad
+        self.shift_register = (self.shift_register >> 1) | (1 << (FOUR_SCORE_NUM_BITS - 1));
+        data
+    }
+
+    fn set_buttons(&mut self, pad: usize, buttons: u8) {
+        if let Some(b) = self.buttons.get_mut(pad) {
+            *b = buttons;
+        }
+    }
+}
+
+// A Famicom port with a pad in the expansion port beside it. The built in
+// pads are read on bit 0 of $4016 and $4017, and the expansion port's on
+// bit 1, so players 3 and 4 are read alongside 1 and 2.
+pub struct FamicomMultitap {
+    pads: [Controller; 2],
+}
+
+impl FamicomMultitap {
+    pub fn new() -> Self {
+        FamicomMultitap {
+            pads: [Controller::new(), Controller::new()],
+        }
+    }
+}
+
+impl InputDevice for FamicomMultitap {
+    fn write_strobe(&mut self, strobe: bool) {
+        for pad in self.pads.iter_mut() {
+            pad.write_strobe(strobe);
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        (self.pads[0].read() & bit!(0)) | ((self.pads[1].read() & bit!(0)) << 1)
+    }
+
+    fn set_buttons(&mut self, pad: usize, buttons: u8) {
+        if let Some(p) = self.pads.get_mut(pad) {
+            p.set_buttons(0, buttons);
+        }
+    }
+}
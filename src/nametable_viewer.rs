@@ -0,0 +1,74 @@
+use addressable;
+use cartridge::Cartridge;
+use nes::NesWithCartridge;
+use frame_buffer::FrameBuffer;
+use ppu::{NAMETABLE_VIEW_WIDTH, NAMETABLE_VIEW_HEIGHT, DISPLAY_WIDTH, DISPLAY_HEIGHT};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ViewportRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+pub struct NametableViewer {
+    frame: FrameBuffer,
+    scroll_x: usize,
+    scroll_y: usize,
+}
+
+impl NametableViewer {
+    pub fn new() -> Self {
+        NametableViewer {
+            frame: FrameBuffer::new(NAMETABLE_VIEW_WIDTH, NAMETABLE_VIEW_HEIGHT),
+            scroll_x: 0,
+            scroll_y: 0,
+        }
+    }
+
+    pub fn update<C: Cartridge>(&mut self, nes: &mut NesWithCartridge<C>) -> addressable::Result<()> {
+        try!(nes.render_nametables(&mut self.frame));
+
+        let (x, y) = nes.ppu.background_top_left_coord();
+        self.scroll_x = x as usize % NAMETABLE_VIEW_WIDTH;
+        self.scroll_y = y as usize % NAMETABLE_VIEW_HEIGHT;
+
+        Ok(())
+    }
+
+    pub fn frame(&self) -> &FrameBuffer {
+        &self.frame
+    }
+
+    // The visible screen wraps around the edges of the nametable view,
+    // so it is split into up to 4 rectangles.
+    pub fn viewport_rects(&self) -> Vec<ViewportRect> {
+        let width_before_wrap = DISPLAY_WIDTH.min(NAMETABLE_VIEW_WIDTH - self.scroll_x);
+        let height_before_wrap = DISPLAY_HEIGHT.min(NAMETABLE_VIEW_HEIGHT - self.scroll_y);
+
+        let mut columns = vec![(self.scroll_x, width_before_wrap)];
+        if width_before_wrap < DISPLAY_WIDTH {
+            columns.push((0, DISPLAY_WIDTH - width_before_wrap));
+        }
+
+        let mut rows = vec![(self.scroll_y, height_before_wrap)];
+        if height_before_wrap < DISPLAY_HEIGHT {
+            rows.push((0, DISPLAY_HEIGHT - height_before_wrap));
+        }
+
+        let mut rects = Vec::new();
+        for &(y, height) in rows.iter() {
+            for &(x, width) in columns.iter() {
+                rects.push(ViewportRect {
+                    x: x,
+                    y: y,
+                    width: width,
+                    height: height,
+                });
+            }
+        }
+
+        rects
+    }
+}
@@ -4,6 +4,7 @@ use addressable;
 use addressable::{Address, Addressable, PpuAddressable};
 use cpu;
 use cpu::Cpu;
+use ppu;
 use ppu::Ppu;
 use io::Io;
 use ram::NesRam;
@@ -11,6 +12,25 @@ use vram::NesVram;
 use palette::Palette;
 use renderer::Frame;
 use ppu_memory_layout::PpuMemoryLayout;
+use image::TvSystem;
+
+// The PPU outputs 3 dots per CPU cycle on NTSC
+const PPU_DOTS_PER_CPU_CYCLE: u64 = 3;
+const DOTS_PER_SCANLINE: u64 = 341;
+
+// from the start of vblank to the end of the pre-render line
+const VBLANK_SCANLINES: u64 = 21;
+// the visible scanlines and the idle line after them
+const RENDER_SCANLINES: u64 = 241;
+
+// CPU cycles lost while the DMC fetches a sample byte
+const DMC_DMA_STALL_CYCLES: u64 = 4;
+
+const APU_STATUS: Address = 0x4015;
+
+// Where subroutines called from outside return to. Nothing is mapped here,
+// so it can't be a real return address.
+const SUBROUTINE_RETURN_ADDRESS: Address = 0x4100;
 
 pub struct NesWithCartridge<C: cartridge::Cartridge> {
     cartridge: C,
@@ -20,18 +40,27 @@ pub struct NesWithCartridge<C: cartridge::Cartridge> {
     ram: NesRam,
     vram: NesVram,
     palette: Palette,
+    // PPU dots elapsed, which the CPU is run to catch up with
+    ppu_dots: u64,
+    // the dot the visible scanlines started on, while they're being drawn
+    render_start_dot: Option<u64>,
+    // the last value on the CPU's data bus
+    data_bus: u8,
 }
 
 impl<C: cartridge::Cartridge> NesWithCartridge<C> {
-    pub fn new(cartridge: C) -> Self {
+    pub fn new(cartridge: C, tv_system: TvSystem) -> Self {
         NesWithCartridge {
             cartridge: cartridge,
             cpu: Cpu::new(),
             ppu: Ppu::new(),
-            io: Io::new(),
+            io: Io::new(tv_system),
             ram: NesRam::new(),
             vram: NesVram::new(),
             palette: Palette::new(),
+            ppu_dots: 0,
+            render_start_dot: None,
+            data_bus: 0,
         }
     }
 
@@ -43,20 +72,42 @@ impl<C: cartridge::Cartridge> NesWithCartridge<C> {
         Ok(())
     }
 
+    // As with the reset button, which restarts the CPU and silences the APU
+    // but leaves memory alone
+    pub fn reset(&mut self) -> cpu::Result<()> {
+        try!(self.write8(APU_STATUS, 0).map_err(cpu::Error::MemoryError));
+        self.init()
+    }
+
     pub fn emulate_frame<F: Frame>(&mut self, frame: &mut F) -> cpu::Result<()> {
         try!(self.vblank_interval());
         try!(self.render_interval(frame));
 
+        self.io.apu.end_frame();
+
         Ok(())
     }
 
+    pub fn render_nametables<F: Frame>(&mut self, frame: &mut F) -> addressable::Result<()> {
+        let mut ppu_memory = PpuMemoryLayout::new(&mut self.cartridge, &mut self.vram, &mut self.palette);
+
+        self.ppu.render_nametables(frame, &mut ppu_memory)
+    }
+
+    pub fn render_sprite_graphic<F: Frame>(&mut self, frame: &mut F, index: usize, x: u8, y: u8) -> addressable::Result<()> {
+        let mut ppu_memory = PpuMemoryLayout::new(&mut self.cartridge, &mut self.vram, &mut self.palette);
+
+        self.ppu.render_sprite_graphic(frame, &mut ppu_memory, index, x, y)
+    }
+
     fn memory_layout(&mut self) -> MemoryLayout<C> {
         MemoryLayout::new(&mut self.cartridge,
                           &mut self.ppu,
                           &mut self.io,
                           &mut self.ram,
                           &mut self.vram,
-                          &mut self.palette)
+                          &mut self.palette,
+                          &mut self.data_bus)
     }
 
     fn vblank_interval(&mut self) -> cpu::Result<()> {
@@ -64,7 +115,7 @@ impl<C: cartridge::Cartridge> NesWithCartridge<C> {
         interrupts = self.ppu.vblank_start(interrupts);
         self.cpu.interrupts = interrupts;
 
-        try!(self.emulate_cpu(2000));
+        try!(self.emulate_cpu(VBLANK_SCANLINES));
 
         Ok(())
     }
@@ -79,28 +130,134 @@ impl<C: cartridge::Cartridge> NesWithCartridge<C> {
 
             try!(self.ppu.render(frame, &mut ppu_memory).map_err(cpu::Error::MemoryError));
         }
+        self.io.frame_rendered(&*frame);
 
-        try!(self.emulate_cpu(2000));
+        self.render_start_dot = Some(self.ppu_dots);
+        let result = self.emulate_cpu(RENDER_SCANLINES);
+        self.render_start_dot = None;
+        self.io.set_scanline(None);
+        try!(result);
 
         self.ppu.render_end();
 
         Ok(())
     }
 
-    fn emulate_cpu(&mut self, num_instructions: usize) -> cpu::Result<()> {
+    // Runs the CPU for the duration of the given number of scanlines
+    fn emulate_cpu(&mut self, num_scanlines: u64) -> cpu::Result<()> {
+        self.ppu_dots += num_scanlines * DOTS_PER_SCANLINE;
+        let end_cycle = self.ppu_dots / PPU_DOTS_PER_CPU_CYCLE;
+
         let mut cpu = self.cpu;
 
-        for _ in 0..num_instructions {
-            match cpu.tick(&mut self.memory_layout()) {
-                Ok(()) => continue,
-                Err(cpu::Error::InfiniteLoop) => break,
-                other => return other,
+        while cpu.cycles < end_cycle {
+            if let Err(e) = self.step_cpu(&mut cpu, end_cycle) {
+                self.cpu = cpu;
+                return Err(e);
             }
         }
 
         self.cpu = cpu;
         Ok(())
     }
+
+    // Runs one instruction, and the APU alongside it
+    fn step_cpu(&mut self, cpu: &mut Cpu, end_cycle: u64) -> cpu::Result<()> {
+        let start_cycle = cpu.cycles;
+        if let Some(render_start_dot) = self.render_start_dot {
+            let scanline = ((start_cycle * PPU_DOTS_PER_CPU_CYCLE).saturating_sub(render_start_dot) /
+                            DOTS_PER_SCANLINE) as usize;
+            self.io.set_scanline(if scanline < ppu::DISPLAY_HEIGHT { Some(scanline) } else { None });
+        }
+        match cpu.tick(&mut self.memory_layout()) {
+            Ok(()) => self.run_apu(cpu, start_cycle),
+            // a CPU spinning in a loop can only leave it through an interrupt
+            Err(cpu::Error::InfiniteLoop) => {
+                self.run_apu(cpu, start_cycle).and_then(|_| self.idle_cpu(cpu, end_cycle))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Calls the subroutine at the given address with the accumulator and X
+    // register set, and runs it as with `resume_subroutine`
+    pub fn call_subroutine(&mut self, address: Address, accumulator: u8, x_index: u8,
+                           end_cycle: u64) -> cpu::Result<bool> {
+        let mut cpu = self.cpu;
+        let result = cpu.call(address, accumulator, x_index, SUBROUTINE_RETURN_ADDRESS,
+                              &mut self.memory_layout());
+        self.cpu = cpu;
+        try!(result);
+
+        self.resume_subroutine(end_cycle)
+    }
+
+    // Runs the CPU until the called subroutine returns or the end cycle is
+    // reached, returning whether the subroutine returned. The PPU isn't run.
+    pub fn resume_subroutine(&mut self, end_cycle: u64) -> cpu::Result<bool> {
+        let mut cpu = self.cpu;
+
+        while cpu.cycles < end_cycle && cpu.program_counter() != SUBROUTINE_RETURN_ADDRESS {
+            if let Err(e) = self.step_cpu(&mut cpu, end_cycle) {
+                self.cpu = cpu;
+                return Err(e);
+            }
+        }
+
+        self.cpu = cpu;
+        Ok(cpu.program_counter() == SUBROUTINE_RETURN_ADDRESS)
+    }
+
+    // Leaves the CPU idle while the APU runs up to the given cycle
+    pub fn idle_until(&mut self, end_cycle: u64) -> cpu::Result<()> {
+        let mut cpu = self.cpu;
+        let start_cycle = cpu.cycles;
+        let result = if start_cycle < end_cycle {
+            cpu.cycles = end_cycle;
+            self.run_apu(&mut cpu, start_cycle)
+        } else {
+            Ok(())
+        };
+        self.cpu = cpu;
+        result
+    }
+
+    pub fn cpu_cycles(&self) -> u64 {
+        self.cpu.cycles
+    }
+
+    fn idle_cpu(&mut self, cpu: &mut Cpu, end_cycle: u64) -> cpu::Result<()> {
+        while cpu.cycles < end_cycle && !cpu.interrupt_pending() {
+            let start_cycle = cpu.cycles;
+            cpu.cycles += 1;
+            try!(self.run_apu(cpu, start_cycle));
+        }
+        Ok(())
+    }
+
+    // Catches the APU and cartridge up with the CPU, performing any DMC
+    // sample fetches, each of which delays the CPU
+    fn run_apu(&mut self, cpu: &mut Cpu, start_cycle: u64) -> cpu::Result<()> {
+        let mut cycle = start_cycle;
+
+        while cycle < cpu.cycles {
+            self.cartridge.cpu_tick();
+            self.cartridge.audio_tick();
+            self.io.apu.set_expansion_output(self.cartridge.audio_output());
+            self.io.apu.tick();
+            cycle += 1;
+
+            if let Some(address) = self.io.apu.dmc_dma_address() {
+                let data = try!(self.memory_layout().read8(address).map_err(cpu::Error::MemoryError));
+                self.io.apu.dmc_dma_complete(data);
+                cpu.cycles += DMC_DMA_STALL_CYCLES;
+            }
+        }
+
+        cpu.interrupts.irq = self.io.apu.irq() || self.cartridge.irq();
+
+        Ok(())
+    }
 }
 
 impl<C: cartridge::Cartridge> Addressable for NesWithCartridge<C> {
@@ -119,6 +276,8 @@ impl<C: cartridge::Cartridge> PpuAddressable for NesWithCartridge<C> {
     }
 
     fn ppu_write8(&mut self, address: Address, data: u8) -> addressable::Result<()> {
-        self.memory_layout().ppu_memory_layout().ppu_write8(address, data)
+        try!(self.memory_layout().ppu_memory_layout().ppu_write8(address, data));
+        self.ppu.pattern_table_written(address);
+        Ok(())
     }
 }
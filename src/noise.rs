@@ -0,0 +1,118 @@
+use envelope::Envelope;
+use length_counter::LengthCounter;
+use image::TvSystem;
+
+const CONTROL: usize = 0;
+const MODE_PERIOD: usize = 2;
+const LENGTH: usize = 3;
+
+const CONTROL_LENGTH_HALT: u8 = bit!(5);
+
+const MODE: u8 = bit!(7);
+const PERIOD_INDEX_MASK: u8 = mask!(4);
+
+const LENGTH_INDEX_SHIFT: usize = 3;
+
+// Timer periods in CPU cycles
+const NTSC_PERIODS: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+const PAL_PERIODS: [u16; 16] = [
+    4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+
+// The shift register is 15 bits, and is loaded with 1 at power on
+const SHIFT_REGISTER_INITIAL: u16 = 1;
+const SHIFT_REGISTER_TOP_SHIFT: usize = 14;
+
+// The bit which is XORed with bit 0 for feedback, in each mode
+const LONG_MODE_TAP: usize = 1;
+const SHORT_MODE_TAP: usize = 6;
+
+pub struct Noise {
+    periods: &'static [u16; 16],
+    // in short mode, the sequence repeats every 93 or 31 steps
+    short_mode: bool,
+    timer_period: u16,
+    timer: u16,
+    shift_register: u16,
+    envelope: Envelope,
+    length_counter: LengthCounter,
+}
+
+impl Noise {
+    pub fn new(tv_system: TvSystem) -> Self {
+        Noise {
+            periods: match tv_system {
+                TvSystem::Ntsc => &NTSC_PERIODS,
+                TvSystem::Pal => &PAL_PERIODS,
+            },
+            short_mode: false,
+            timer_period: NTSC_PERIODS[0],
+            timer: 0,
+            shift_register: SHIFT_REGISTER_INITIAL,
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    // Writes one of the channel's 4 registers, the second of which is unused
+    pub fn write(&mut self, register: usize, data: u8) {
+        match register {
+            CONTROL => {
+                self.length_counter.set_halt(data & CONTROL_LENGTH_HALT != 0);
+                self.envelope.write(data);
+            }
+            MODE_PERIOD => {
+                self.short_mode = data & MODE != 0;
+                self.timer_period = self.periods[(data & PERIOD_INDEX_MASK) as usize];
+            }
+            LENGTH => {
+                self.length_counter.load(data >> LENGTH_INDEX_SHIFT);
+                self.envelope.restart();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn active(&self) -> bool {
+        self.length_counter.active()
+    }
+
+    // Clocked every CPU cycle
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            self.clock_shift_register();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_shift_register(&mut self) {
+        let tap = if self.short_mode { SHORT_MODE_TAP } else { LONG_MODE_TAP };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & bit!(0);
+        self.shift_register = (self.shift_register >> 1) | (feedback << SHIFT_REGISTER_TOP_SHIFT);
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    // Current output level, from 0 to 15
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.active() || self.shift_register & bit!(0) != 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
@@ -24,6 +24,8 @@ pub struct NromCartridgeWithMirror<M: Mirror> {
 
 impl<M: Mirror> cartridge::Cartridge for NromCartridgeWithMirror<M> {}
 
+impl<M: Mirror> cartridge::CartridgeAudio for NromCartridgeWithMirror<M> {}
+
 pub enum NromCartridge {
     HorizontalMirroring(NromCartridgeWithMirror<HorizontalMirror>),
     VerticalMirroring(NromCartridgeWithMirror<VerticalMirror>),
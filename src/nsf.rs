@@ -0,0 +1,300 @@
+use std::{fs, io, result};
+use std::io::Read;
+use std::ops::Range;
+
+use addressable::Address;
+use image::TvSystem;
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidChecksum,
+    InvalidHeader,
+    FileTooSmall,
+    MissingChunk(&'static str),
+    UnknownRequiredChunk(String),
+    IoError(io::Error),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+// Expansion chip flags
+pub const EXPANSION_VRC6: u8 = bit!(0);
+pub const EXPANSION_VRC7: u8 = bit!(1);
+pub const EXPANSION_FDS: u8 = bit!(2);
+pub const EXPANSION_MMC5: u8 = bit!(3);
+pub const EXPANSION_NAMCO_163: u8 = bit!(4);
+pub const EXPANSION_SUNSOFT_5B: u8 = bit!(5);
+
+const EXPANSION_NAMES: [(u8, &'static str); 6] = [
+    (EXPANSION_VRC6, "VRC6"),
+    (EXPANSION_VRC7, "VRC7"),
+    (EXPANSION_FDS, "FDS"),
+    (EXPANSION_MMC5, "MMC5"),
+    (EXPANSION_NAMCO_163, "Namco 163"),
+    (EXPANSION_SUNSOFT_5B, "Sunsoft 5B"),
+];
+
+pub const NUM_BANKS: usize = 8;
+
+const NTSC_DEFAULT_SPEED: u16 = 16639;
+const PAL_DEFAULT_SPEED: u16 = 19997;
+
+const TV_SYSTEM_PAL: u8 = bit!(0);
+const TV_SYSTEM_DUAL: u8 = bit!(1);
+
+// NSF header fields
+const HEADER_NUM_BYTES: usize = 0x80;
+const HEADER_CHECKSUM: Range<usize> = Range { start: 0, end: 5 };
+const HEADER_TOTAL_SONGS: usize = 0x06;
+const HEADER_STARTING_SONG: usize = 0x07;
+const HEADER_LOAD_ADDRESS: usize = 0x08;
+const HEADER_INIT_ADDRESS: usize = 0x0a;
+const HEADER_PLAY_ADDRESS: usize = 0x0c;
+const HEADER_NAME: Range<usize> = Range { start: 0x0e, end: 0x2e };
+const HEADER_ARTIST: Range<usize> = Range { start: 0x2e, end: 0x4e };
+const HEADER_COPYRIGHT: Range<usize> = Range { start: 0x4e, end: 0x6e };
+const HEADER_NTSC_SPEED: usize = 0x6e;
+const HEADER_BANKSWITCH: Range<usize> = Range { start: 0x70, end: 0x78 };
+const HEADER_PAL_SPEED: usize = 0x78;
+const HEADER_TV_SYSTEM: usize = 0x7a;
+const HEADER_EXPANSION: usize = 0x7b;
+
+// NSFe chunks
+const NSFE_CHECKSUM: &'static [u8] = b"NSFE";
+const CHUNK_HEADER_NUM_BYTES: usize = 8;
+const CHUNK_INFO: &'static [u8] = b"INFO";
+const CHUNK_DATA: &'static [u8] = b"DATA";
+const CHUNK_BANK: &'static [u8] = b"BANK";
+const CHUNK_RATE: &'static [u8] = b"RATE";
+const CHUNK_AUTH: &'static [u8] = b"auth";
+const CHUNK_TRACK_LABELS: &'static [u8] = b"tlbl";
+const CHUNK_END: &'static [u8] = b"NEND";
+
+const INFO_MIN_NUM_BYTES: usize = 8;
+const INFO_LOAD_ADDRESS: usize = 0;
+const INFO_INIT_ADDRESS: usize = 2;
+const INFO_PLAY_ADDRESS: usize = 4;
+const INFO_TV_SYSTEM: usize = 6;
+const INFO_EXPANSION: usize = 7;
+const INFO_TOTAL_SONGS: usize = 8;
+const INFO_STARTING_SONG: usize = 9;
+
+#[derive(Debug, Clone)]
+pub struct NsfHeader {
+    pub num_songs: u8,
+    // numbered from 1
+    pub starting_song: u8,
+    pub load_address: Address,
+    pub init_address: Address,
+    pub play_address: Address,
+    pub name: String,
+    pub artist: String,
+    pub copyright: String,
+    pub track_names: Vec<String>,
+    // microseconds between calls to the play routine
+    pub ntsc_speed: u16,
+    pub pal_speed: u16,
+    pub bank_switch: Option<[u8; NUM_BANKS]>,
+    pub tv_system: TvSystem,
+    pub expansion_chips: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct NsfImage {
+    pub header: NsfHeader,
+    pub data: Vec<u8>,
+}
+
+impl NsfHeader {
+    // Microseconds between play calls; some rips leave the field zero
+    pub fn speed(&self) -> u16 {
+        match self.tv_system {
+            TvSystem::Ntsc if self.ntsc_speed == 0 => NTSC_DEFAULT_SPEED,
+            TvSystem::Ntsc => self.ntsc_speed,
+            TvSystem::Pal if self.pal_speed == 0 => PAL_DEFAULT_SPEED,
+            TvSystem::Pal => self.pal_speed,
+        }
+    }
+
+    pub fn expansion_chip_names(&self) -> Vec<&'static str> {
+        EXPANSION_NAMES.iter()
+            .filter(|&&(flag, _)| self.expansion_chips & flag != 0)
+            .map(|&(_, name)| name)
+            .collect()
+    }
+
+    pub fn track_name(&self, track: u8) -> Option<&str> {
+        track.checked_sub(1)
+            .and_then(|index| self.track_names.get(index as usize))
+            .map(|s| &s[..])
+    }
+}
+
+pub fn parse_file(mut file: fs::File) -> Result<NsfImage> {
+    let mut buffer = Vec::new();
+
+    if let Err(e) = file.read_to_end(&mut buffer) {
+        return Err(Error::IoError(e));
+    }
+
+    if buffer.starts_with(NSFE_CHECKSUM) {
+        parse_nsfe(&buffer[NSFE_CHECKSUM.len()..])
+    } else {
+        parse_nsf(&buffer)
+    }
+}
+
+fn parse_nsf(buffer: &[u8]) -> Result<NsfImage> {
+    if buffer.len() < HEADER_NUM_BYTES {
+        return Err(Error::FileTooSmall);
+    }
+
+    let header = &buffer[0..HEADER_NUM_BYTES];
+    if &header[HEADER_CHECKSUM] != b"NESM\x1a" {
+        return Err(Error::InvalidChecksum);
+    }
+
+    let mut bank_switch = [0; NUM_BANKS];
+    bank_switch.copy_from_slice(&header[HEADER_BANKSWITCH]);
+    let bank_switch = if bank_switch.iter().any(|&b| b != 0) {
+        Some(bank_switch)
+    } else {
+        None
+    };
+
+    Ok(NsfImage {
+        header: NsfHeader {
+            num_songs: header[HEADER_TOTAL_SONGS],
+            starting_song: header[HEADER_STARTING_SONG],
+            load_address: read16_le(header, HEADER_LOAD_ADDRESS),
+            init_address: read16_le(header, HEADER_INIT_ADDRESS),
+            play_address: read16_le(header, HEADER_PLAY_ADDRESS),
+            name: read_string(&header[HEADER_NAME]),
+            artist: read_string(&header[HEADER_ARTIST]),
+            copyright: read_string(&header[HEADER_COPYRIGHT]),
+            track_names: Vec::new(),
+            ntsc_speed: read16_le(header, HEADER_NTSC_SPEED),
+            pal_speed: read16_le(header, HEADER_PAL_SPEED),
+            bank_switch: bank_switch,
+            tv_system: tv_system(header[HEADER_TV_SYSTEM]),
+            expansion_chips: header[HEADER_EXPANSION],
+        },
+        data: buffer[HEADER_NUM_BYTES..].to_vec(),
+    })
+}
+
+// NSFe files are a sequence of chunks, each a length, a four character ID
+// and the data. Chunks with an upper case first letter must be understood.
+fn parse_nsfe(mut buffer: &[u8]) -> Result<NsfImage> {
+    let mut info = None;
+    let mut data = None;
+    let mut bank_switch = None;
+    let mut speeds = None;
+    let mut strings = Vec::new();
+    let mut track_names = Vec::new();
+
+    loop {
+        if buffer.len() < CHUNK_HEADER_NUM_BYTES {
+            return Err(Error::MissingChunk("NEND"));
+        }
+        let length = read32_le(buffer, 0) as usize;
+        let id = &buffer[4..CHUNK_HEADER_NUM_BYTES];
+        buffer = &buffer[CHUNK_HEADER_NUM_BYTES..];
+        if buffer.len() < length {
+            return Err(Error::FileTooSmall);
+        }
+        let chunk = &buffer[0..length];
+        buffer = &buffer[length..];
+
+        if id == CHUNK_INFO {
+            if chunk.len() < INFO_MIN_NUM_BYTES {
+                return Err(Error::InvalidHeader);
+            }
+            info = Some(chunk);
+        } else if id == CHUNK_DATA {
+            data = Some(chunk);
+        } else if id == CHUNK_BANK {
+            let mut banks = [0; NUM_BANKS];
+            for (bank, &b) in banks.iter_mut().zip(chunk.iter()) {
+                *bank = b;
+            }
+            bank_switch = Some(banks);
+        } else if id == CHUNK_RATE {
+            if chunk.len() >= 2 {
+                let ntsc = read16_le(chunk, 0);
+                let pal = if chunk.len() >= 4 { read16_le(chunk, 2) } else { PAL_DEFAULT_SPEED };
+                speeds = Some((ntsc, pal));
+            }
+        } else if id == CHUNK_AUTH {
+            strings = read_strings(chunk);
+        } else if id == CHUNK_TRACK_LABELS {
+            track_names = read_strings(chunk);
+        } else if id == CHUNK_END {
+            break;
+        } else if id[0].is_ascii_uppercase() {
+            return Err(Error::UnknownRequiredChunk(String::from_utf8_lossy(id).into_owned()));
+        }
+    }
+
+    let info = match info {
+        Some(info) => info,
+        None => return Err(Error::MissingChunk("INFO")),
+    };
+    let data = match data {
+        Some(data) => data,
+        None => return Err(Error::MissingChunk("DATA")),
+    };
+    let (ntsc_speed, pal_speed) = speeds.unwrap_or((NTSC_DEFAULT_SPEED, PAL_DEFAULT_SPEED));
+    let mut strings = strings.into_iter();
+
+    Ok(NsfImage {
+        header: NsfHeader {
+            num_songs: info.get(INFO_TOTAL_SONGS).cloned().unwrap_or(1),
+            // stored from 0 in NSFe
+            starting_song: info.get(INFO_STARTING_SONG).cloned().unwrap_or(0) + 1,
+            load_address: read16_le(info, INFO_LOAD_ADDRESS),
+            init_address: read16_le(info, INFO_INIT_ADDRESS),
+            play_address: read16_le(info, INFO_PLAY_ADDRESS),
+            name: strings.next().unwrap_or_default(),
+            artist: strings.next().unwrap_or_default(),
+            copyright: strings.next().unwrap_or_default(),
+            track_names: track_names,
+            ntsc_speed: ntsc_speed,
+            pal_speed: pal_speed,
+            bank_switch: bank_switch,
+            tv_system: tv_system(info[INFO_TV_SYSTEM]),
+            expansion_chips: info[INFO_EXPANSION],
+        },
+        data: data.to_vec(),
+    })
+}
+
+// Tunes for both systems are played as NTSC
+fn tv_system(flags: u8) -> TvSystem {
+    if flags & TV_SYSTEM_PAL != 0 && flags & TV_SYSTEM_DUAL == 0 {
+        TvSystem::Pal
+    } else {
+        TvSystem::Ntsc
+    }
+}
+
+fn read16_le(data: &[u8], index: usize) -> u16 {
+    data[index] as u16 | ((data[index + 1] as u16) << 8)
+}
+
+fn read32_le(data: &[u8], index: usize) -> u32 {
+    read16_le(data, index) as u32 | ((read16_le(data, index + 2) as u32) << 16)
+}
+
+// Reads a null-padded string
+fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[0..end]).into_owned()
+}
+
+// Reads a list of null-terminated strings
+fn read_strings(data: &[u8]) -> Vec<String> {
+    data.split(|&b| b == 0)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
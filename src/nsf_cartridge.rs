@@ -0,0 +1,187 @@
+use cartridge;
+use addressable;
+use addressable::{PpuAddressable, Address};
+use vram::NesVram;
+use mirror::{Mirror, HorizontalMirror};
+use nsf;
+use nsf::NsfImage;
+use vrc6_audio::Vrc6Audio;
+use sunsoft_5b_audio::Sunsoft5bAudio;
+
+const BANK_SIZE: usize = 0x1000;
+const ROM_START: Address = 0x8000;
+
+// Writes here select the 4KB bank at each of $8000-$ffff
+const BANK_REGISTERS_START: Address = 0x5ff8;
+const BANK_REGISTERS_END: Address = 0x5fff;
+
+// The expansion chips' registers, within the cartridge's ROM space
+const VRC6_REGISTERS_START: Address = 0x9000;
+const VRC6_REGISTERS_END: Address = 0xb002;
+const VRC6_REGISTER_MASK: Address = 0xf003;
+const SUNSOFT_5B_REGISTERS_START: Address = 0xc000;
+const SUNSOFT_5B_REGISTER_MASK: Address = 0xe000;
+
+const CHR_RAM_SIZE: usize = 0x2000;
+
+// A synthetic cartridge which holds an NSF's data behind the NSF bank
+// switching scheme, with whichever of its expansion chips are supported
+pub struct NsfCartridge {
+    rom: Vec<u8>,
+    banks: [u8; nsf::NUM_BANKS],
+    ram: Vec<u8>,
+    chr_ram: Vec<u8>,
+    vrc6: Option<Vrc6Audio>,
+    sunsoft_5b: Option<Sunsoft5bAudio>,
+}
+
+impl NsfCartridge {
+    pub fn new(image: &NsfImage) -> cartridge::Result<Self> {
+        let header = &image.header;
+
+        // banked data is aligned within its first bank, and unbanked data
+        // is placed at its load address
+        let (padding, banks) = match header.bank_switch {
+            Some(banks) => ((header.load_address as usize) % BANK_SIZE, banks),
+            None => {
+                if header.load_address < ROM_START {
+                    return Err(cartridge::Error::InvalidRomSize);
+                }
+                ((header.load_address - ROM_START) as usize, [0, 1, 2, 3, 4, 5, 6, 7])
+            }
+        };
+
+        let mut rom = vec![0; padding];
+        rom.extend_from_slice(&image.data);
+        let num_banks = (rom.len() + BANK_SIZE - 1) / BANK_SIZE;
+        rom.resize(num_banks * BANK_SIZE, 0);
+
+        let vrc6 = if header.expansion_chips & nsf::EXPANSION_VRC6 != 0 {
+            Some(Vrc6Audio::new())
+        } else {
+            None
+        };
+        let sunsoft_5b = if header.expansion_chips & nsf::EXPANSION_SUNSOFT_5B != 0 {
+            Some(Sunsoft5bAudio::new())
+        } else {
+            None
+        };
+
+        Ok(NsfCartridge {
+            rom: rom,
+            banks: banks,
+            ram: vec![0; cartridge::RAM_BANK_SIZE],
+            chr_ram: vec![0; CHR_RAM_SIZE],
+            vrc6: vrc6,
+            sunsoft_5b: sunsoft_5b,
+        })
+    }
+
+    // The expansion chips supported here, out of those the NSF asks for
+    pub fn supported_expansion_chips() -> u8 {
+        nsf::EXPANSION_VRC6 | nsf::EXPANSION_SUNSOFT_5B
+    }
+
+    fn rom_read(&self, address: Address) -> u8 {
+        let offset = (address - ROM_START) as usize;
+        let bank = self.banks[offset / BANK_SIZE] as usize;
+        self.rom.get(bank * BANK_SIZE + offset % BANK_SIZE).cloned().unwrap_or(0)
+    }
+
+    // Writes that don't reach a sound chip are ignored, as by ROM
+    fn rom_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        if let Some(ref mut vrc6) = self.vrc6 {
+            if let VRC6_REGISTERS_START...VRC6_REGISTERS_END = address {
+                vrc6.write(address & VRC6_REGISTER_MASK, data);
+            }
+        }
+        if let Some(ref mut sunsoft_5b) = self.sunsoft_5b {
+            if address >= SUNSOFT_5B_REGISTERS_START {
+                sunsoft_5b.write(address & SUNSOFT_5B_REGISTER_MASK, data);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl cartridge::Cartridge for NsfCartridge {
+    fn expansion_read(&mut self, address: Address) -> addressable::Result<Option<u8>> {
+        match address {
+            BANK_REGISTERS_START...BANK_REGISTERS_END => {
+                Ok(Some(self.banks[(address - BANK_REGISTERS_START) as usize]))
+            }
+            // tunes may probe the rest of the range, which nothing drives
+            _ => Ok(None),
+        }
+    }
+
+    fn expansion_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        match address {
+            BANK_REGISTERS_START...BANK_REGISTERS_END => {
+                self.banks[(address - BANK_REGISTERS_START) as usize] = data;
+                Ok(())
+            }
+            _ => Err(addressable::Error::UnimplementedWrite(address)),
+        }
+    }
+}
+
+impl cartridge::CartridgeAudio for NsfCartridge {
+    fn audio_tick(&mut self) {
+        if let Some(ref mut vrc6) = self.vrc6 {
+            vrc6.tick();
+        }
+        if let Some(ref mut sunsoft_5b) = self.sunsoft_5b {
+            sunsoft_5b.tick();
+        }
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.vrc6.as_ref().map_or(0.0, |chip| chip.output()) +
+        self.sunsoft_5b.as_ref().map_or(0.0, |chip| chip.output())
+    }
+}
+
+impl cartridge::CpuInterface for NsfCartridge {
+    fn ram_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.ram[address as usize])
+    }
+
+    fn ram_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.ram[address as usize] = data;
+        Ok(())
+    }
+
+    fn lower_rom_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.rom_read(ROM_START + address))
+    }
+
+    fn lower_rom_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.rom_write(ROM_START + address, data)
+    }
+
+    fn upper_rom_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.rom_read(ROM_START + cartridge::ROM_BANK_SIZE as Address + address))
+    }
+
+    fn upper_rom_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.rom_write(ROM_START + cartridge::ROM_BANK_SIZE as Address + address, data)
+    }
+}
+
+// Nothing is displayed, but tunes may still write to the PPU
+impl cartridge::PpuInterface for NsfCartridge {
+    fn pattern_table_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.chr_ram[address as usize])
+    }
+    fn pattern_table_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.chr_ram[address as usize] = data;
+        Ok(())
+    }
+    fn name_table_read(&mut self, address: Address, ram: &mut NesVram) -> addressable::Result<u8> {
+        ram.ppu_read8(HorizontalMirror::mirror(address))
+    }
+    fn name_table_write(&mut self, address: Address, data: u8, ram: &mut NesVram) -> addressable::Result<()> {
+        ram.ppu_write8(HorizontalMirror::mirror(address), data)
+    }
+}
@@ -0,0 +1,228 @@
+use std::result;
+
+use cpu;
+use cartridge;
+use nes::NesWithCartridge;
+use nsf::{NsfImage, NsfHeader};
+use nsf_cartridge::NsfCartridge;
+use addressable::{Addressable, Address};
+use image::TvSystem;
+use mixer;
+use mixer::{AudioOptions, Mixer};
+use frontend::Frontend;
+use wav::{WavWriter, ChannelRecording};
+
+// The APU's state before a tune is initialised
+const APU_REGISTERS_START: Address = 0x4000;
+const APU_REGISTERS_END: Address = 0x4013;
+const APU_STATUS: Address = 0x4015;
+const APU_STATUS_INIT: u8 = 0x0f;
+const APU_FRAME_COUNTER: Address = 0x4017;
+const APU_FRAME_COUNTER_INIT: u8 = 0x40;
+
+// How long the init routine may run for before playing starts regardless
+const INIT_MAX_SECONDS: f64 = 1.0;
+
+const MICROSECONDS_PER_SECOND: f64 = 1000000.0;
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    CartridgeError(cartridge::Error),
+    CpuError(cpu::Error),
+}
+
+// Plays the tracks of an NSF by calling its init routine, then its play
+// routine at the rate it asks for
+pub struct NsfPlayer {
+    image: NsfImage,
+    nes: NesWithCartridge<NsfCartridge>,
+    audio: AudioOptions,
+    sample_rate: u32,
+    track: u8,
+    // CPU cycles between calls to the play routine
+    play_period: f64,
+    next_play: f64,
+    playing: bool,
+}
+
+impl NsfPlayer {
+    pub fn new(image: NsfImage, audio: AudioOptions, track: u8) -> cartridge::Result<Self> {
+        let tv_system = image.header.tv_system;
+        let clock_rate = mixer::cpu_clock_rate(tv_system);
+        let play_period = image.header.speed() as f64 * clock_rate / MICROSECONDS_PER_SECOND;
+        let nes = NesWithCartridge::new(try!(NsfCartridge::new(&image)), tv_system);
+
+        let mut player = NsfPlayer {
+            image: image,
+            nes: nes,
+            audio: audio,
+            sample_rate: mixer::DEFAULT_SAMPLE_RATE,
+            track: track,
+            play_period: play_period,
+            next_play: 0.0,
+            playing: false,
+        };
+        player.audio.apply(player.nes.io.apu.mixer());
+        Ok(player)
+    }
+
+    pub fn header(&self) -> &NsfHeader {
+        &self.image.header
+    }
+
+    pub fn audio_options(&self) -> &AudioOptions {
+        &self.audio
+    }
+
+    pub fn track(&self) -> u8 {
+        self.track
+    }
+
+    pub fn mixer(&mut self) -> &mut Mixer {
+        self.nes.io.apu.mixer()
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        self.nes.io.apu.samples()
+    }
+
+    pub fn recorded_samples(&mut self) -> &[f32] {
+        self.nes.io.apu.mixer().recorded_samples()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.mixer().set_sample_rate(sample_rate);
+    }
+
+    pub fn print_info(&self) {
+        let header = &self.image.header;
+        println!("Name:      {}", header.name);
+        println!("Artist:    {}", header.artist);
+        println!("Copyright: {}", header.copyright);
+        println!("Tracks:    {}", header.num_songs);
+        println!("System:    {:?}", header.tv_system);
+
+        let chips = header.expansion_chip_names();
+        if !chips.is_empty() {
+            println!("Expansion: {}", chips.join(", "));
+        }
+        if header.expansion_chips & !NsfCartridge::supported_expansion_chips() != 0 {
+            println!("Warning: some of this tune's expansion chips aren't supported");
+        }
+    }
+
+    // Resets the console and initialises the given track, numbered from 1
+    pub fn start_track(&mut self, track: u8) -> Result<()> {
+        let tv_system = self.image.header.tv_system;
+        let cartridge = try!(NsfCartridge::new(&self.image).map_err(Error::CartridgeError));
+        self.nes = NesWithCartridge::new(cartridge, tv_system);
+        self.nes.io.apu.mixer().set_sample_rate(self.sample_rate);
+        self.audio.apply(self.nes.io.apu.mixer());
+        self.track = track;
+
+        try!(self.init_track(track).map_err(Error::CpuError));
+
+        match self.image.header.track_name(track) {
+            Some(name) => println!("Track {}/{}: {}", track, self.image.header.num_songs, name),
+            None => println!("Track {}/{}", track, self.image.header.num_songs),
+        }
+
+        Ok(())
+    }
+
+    fn init_track(&mut self, track: u8) -> cpu::Result<()> {
+        let tv_system = self.image.header.tv_system;
+        for address in APU_REGISTERS_START..(APU_REGISTERS_END + 1) {
+            try!(self.nes.write8(address, 0).map_err(cpu::Error::MemoryError));
+        }
+        try!(self.nes.write8(APU_STATUS, APU_STATUS_INIT).map_err(cpu::Error::MemoryError));
+        try!(self.nes.write8(APU_FRAME_COUNTER, APU_FRAME_COUNTER_INIT).map_err(cpu::Error::MemoryError));
+
+        let region = match tv_system {
+            TvSystem::Ntsc => 0,
+            TvSystem::Pal => 1,
+        };
+        let init_cycles = (mixer::cpu_clock_rate(tv_system) * INIT_MAX_SECONDS) as u64;
+        let init_address = self.image.header.init_address;
+        try!(self.nes.call_subroutine(init_address, track - 1, region, init_cycles));
+
+        self.next_play = self.nes.cpu_cycles() as f64;
+        self.playing = false;
+        Ok(())
+    }
+
+    // Runs one period of the play routine and produces its audio samples.
+    // A play routine still running at the end of the period carries on in
+    // the next instead of being called again.
+    pub fn play_frame(&mut self) -> cpu::Result<()> {
+        self.next_play += self.play_period;
+        let end_cycle = self.next_play as u64;
+
+        let returned = if self.playing {
+            try!(self.nes.resume_subroutine(end_cycle))
+        } else {
+            let play_address = self.image.header.play_address;
+            try!(self.nes.call_subroutine(play_address, 0, 0, end_cycle))
+        };
+        self.playing = !returned;
+
+        try!(self.nes.idle_until(end_cycle));
+        self.nes.io.apu.end_frame();
+
+        Ok(())
+    }
+}
+
+// Renders a track to WAV files without any audio device
+pub struct HeadlessNsfFrontend {
+    player: NsfPlayer,
+    num_frames: usize,
+}
+
+impl HeadlessNsfFrontend {
+    pub fn new(player: NsfPlayer, num_frames: usize) -> Self {
+        HeadlessNsfFrontend {
+            player: player,
+            num_frames: num_frames,
+        }
+    }
+}
+
+impl Frontend for HeadlessNsfFrontend {
+    fn print_rom_dump(&mut self) {
+        self.player.print_info();
+    }
+
+    fn run(&mut self) {
+        let track = self.player.track();
+        self.player.start_track(track).expect("Failed to start track");
+
+        let sample_rate = self.player.mixer().sample_rate();
+        let mut recording = self.player.audio.record_path.as_ref().map(|path| {
+            WavWriter::create(path, sample_rate).expect("Failed to create audio recording")
+        });
+        let mut channel_recording = self.player.audio.channel_record_prefix.as_ref().map(|prefix| {
+            ChannelRecording::create(prefix, sample_rate).expect("Failed to create channel recordings")
+        });
+
+        for _ in 0..self.num_frames {
+            self.player.play_frame().expect("Emulation failed");
+            if let Some(ref mut wav) = recording {
+                wav.write_samples(self.player.recorded_samples()).expect("Failed to record audio");
+            }
+            if let Some(ref mut channels) = channel_recording {
+                channels.write_frame(self.player.mixer()).expect("Failed to record channels");
+            }
+        }
+
+        if let Some(wav) = recording {
+            wav.finish().expect("Failed to record audio");
+        }
+        if let Some(channels) = channel_recording {
+            channels.finish().expect("Failed to record channels");
+        }
+    }
+}
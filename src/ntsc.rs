@@ -0,0 +1,333 @@
+use std::f32::consts::PI;
+
+use renderer::{Colour, COLOUR_MASK, EMPHASIS_SHIFT};
+use frame_buffer::{FrameBuffer, RgbBuffer};
+use rgb_palette::NUM_ENTRIES;
+
+// The PPU outputs 8 samples of composite signal per pixel, and a full
+// cycle of the colour subcarrier takes 12 samples.
+const SAMPLES_PER_PIXEL: usize = 8;
+const SAMPLES_PER_CYCLE: usize = 12;
+
+// 341 dots of 8 samples each is 2728 samples per scanline, which shifts
+// the subcarrier phase by 4 samples from one scanline to the next.
+const SCANLINE_PHASE_SHIFT: usize = 4;
+const NUM_FRAME_PHASES: usize = 3;
+
+// Signal voltages relative to sync
+const BLACK_LEVEL: f32 = 0.518;
+const WHITE_LEVEL: f32 = 1.962;
+const EMPHASIS_ATTENUATION: f32 = 0.746;
+const LOW_LEVELS: [f32; 4] = [0.350, 0.518, 0.962, 1.550];
+const HIGH_LEVELS: [f32; 4] = [1.094, 1.506, 1.962, 1.962];
+
+// Phase (in samples) of the centre of each emphasis bit's attenuation window
+const EMPHASIS_PHASES: [usize; 3] = [0, 4, 8];
+
+// The B-Y axis is opposite the colour burst, which is generated with the
+// waveform of colour $x8.
+const U_AXIS_PHASE: f32 = 0.5;
+
+// nes_ntsc widens each group of 3 input pixels into 7 output pixels
+const OUTPUT_WIDTH_NUMERATOR: usize = 7;
+const OUTPUT_WIDTH_DENOMINATOR: usize = 3;
+
+pub fn output_width(input_width: usize) -> usize {
+    if input_width == 0 {
+        return 0;
+    }
+    ((input_width - 1) / OUTPUT_WIDTH_DENOMINATOR + 1) * OUTPUT_WIDTH_NUMERATOR
+}
+
+// All adjustments range from -1 to 1, with 0 being a typical composite
+// signal, in the style of nes_ntsc.
+#[derive(Debug, Clone, Copy)]
+pub struct NtscSettings {
+    // in degrees
+    pub hue: f32,
+    pub saturation: f32,
+    pub contrast: f32,
+    pub brightness: f32,
+    pub sharpness: f32,
+    pub gamma: f32,
+    // chroma crosstalk into luma
+    pub artifacts: f32,
+    // luma crosstalk into chroma
+    pub fringing: f32,
+    // horizontal colour blur
+    pub bleed: f32,
+    // average two frames' subcarrier phases to remove flicker
+    pub merge_fields: bool,
+}
+
+impl NtscSettings {
+    pub fn composite() -> Self {
+        NtscSettings {
+            hue: 0.0,
+            saturation: 0.0,
+            contrast: 0.0,
+            brightness: 0.0,
+            sharpness: 0.0,
+            gamma: 0.0,
+            artifacts: 0.0,
+            fringing: 0.0,
+            bleed: 0.0,
+            merge_fields: true,
+        }
+    }
+
+    pub fn svideo() -> Self {
+        NtscSettings {
+            sharpness: 0.2,
+            artifacts: -1.0,
+            fringing: -1.0,
+            ..Self::composite()
+        }
+    }
+
+    pub fn rgb() -> Self {
+        NtscSettings {
+            sharpness: 0.2,
+            artifacts: -1.0,
+            fringing: -1.0,
+            bleed: -1.0,
+            ..Self::composite()
+        }
+    }
+
+    pub fn monochrome() -> Self {
+        NtscSettings {
+            saturation: -1.0,
+            sharpness: 0.2,
+            artifacts: -0.2,
+            fringing: -0.2,
+            bleed: -1.0,
+            ..Self::composite()
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "composite" => Some(Self::composite()),
+            "svideo" => Some(Self::svideo()),
+            "rgb" => Some(Self::rgb()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+}
+
+pub struct NtscFilter {
+    settings: NtscSettings,
+    // composite signal for each colour at each subcarrier phase
+    signal: Vec<[f32; SAMPLES_PER_CYCLE]>,
+    // the signal of each colour averaged over a full cycle
+    luma: Vec<f32>,
+    luma_window: usize,
+    chroma_window: usize,
+    // the subcarrier's cosine and sine at each phase, for demodulating
+    carrier: [(f32, f32); SAMPLES_PER_CYCLE],
+    frame_phase: usize,
+    // running sums of a scanline's luma and demodulated chroma, from which
+    // each output pixel's window is taken as a difference
+    luma_sums: Vec<f32>,
+    u_sums: Vec<f32>,
+    v_sums: Vec<f32>,
+    line_yuv: Vec<(f32, f32, f32)>,
+}
+
+impl NtscFilter {
+    pub fn new(settings: NtscSettings) -> Self {
+        let mut signal = Vec::with_capacity(NUM_ENTRIES);
+        let mut luma = Vec::with_capacity(NUM_ENTRIES);
+
+        for colour in 0..NUM_ENTRIES {
+            let mut samples = [0.0; SAMPLES_PER_CYCLE];
+            for phase in 0..SAMPLES_PER_CYCLE {
+                samples[phase] = Self::generate_signal(colour as Colour, phase);
+            }
+            luma.push(samples.iter().fold(0.0, |acc, s| acc + s) / SAMPLES_PER_CYCLE as f32);
+            signal.push(samples);
+        }
+
+        // sharper luma uses fewer samples, letting more of the subcarrier through
+        let luma_window = ((SAMPLES_PER_CYCLE as f32) * (1.0 - settings.sharpness * 0.5)).round() as usize;
+
+        // chroma is always decoded over whole cycles so flat areas aren't tinted
+        let chroma_cycles = (2.0 + settings.bleed).round().max(1.0) as usize;
+
+        let hue_offset = settings.hue / 360.0 * SAMPLES_PER_CYCLE as f32;
+        let mut carrier = [(0.0, 0.0); SAMPLES_PER_CYCLE];
+        for (phase, entry) in carrier.iter_mut().enumerate() {
+            let angle = PI * (phase as f32 - U_AXIS_PHASE - hue_offset) / 6.0;
+            *entry = (angle.cos(), angle.sin());
+        }
+
+        NtscFilter {
+            settings: settings,
+            signal: signal,
+            luma: luma,
+            luma_window: luma_window.max(1),
+            chroma_window: chroma_cycles * SAMPLES_PER_CYCLE,
+            carrier: carrier,
+            frame_phase: 0,
+            luma_sums: Vec::new(),
+            u_sums: Vec::new(),
+            v_sums: Vec::new(),
+            line_yuv: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &NtscSettings {
+        &self.settings
+    }
+
+    fn in_colour_phase(colour: usize, phase: usize) -> bool {
+        (colour + phase) % SAMPLES_PER_CYCLE < SAMPLES_PER_CYCLE / 2
+    }
+
+    // Normalized composite signal, where 0 is black and 1 is white
+    fn generate_signal(colour: Colour, phase: usize) -> f32 {
+        let hue = (colour & mask!(4)) as usize;
+        let mut level = ((colour & COLOUR_MASK) >> 4) as usize;
+        let emphasis = (colour >> EMPHASIS_SHIFT) as usize;
+
+        // colours $xE and $xF are always output at the level of $1D
+        if hue > 13 {
+            level = 1;
+        }
+
+        let mut low = LOW_LEVELS[level];
+        let mut high = HIGH_LEVELS[level];
+        if hue == 0 {
+            low = high;
+        }
+        if hue > 12 {
+            high = low;
+        }
+
+        let mut signal = if Self::in_colour_phase(hue, phase) { high } else { low };
+
+        for (bit, &emphasis_phase) in EMPHASIS_PHASES.iter().enumerate() {
+            if emphasis & bit!(bit) != 0 && Self::in_colour_phase(emphasis_phase, phase) {
+                signal *= EMPHASIS_ATTENUATION;
+                break;
+            }
+        }
+
+        (signal - BLACK_LEVEL) / (WHITE_LEVEL - BLACK_LEVEL)
+    }
+
+    pub fn apply(&mut self, frame: &FrameBuffer, output: &mut RgbBuffer) {
+        let width = output_width(frame.width());
+        output.resize(width, frame.height());
+
+        let phase = self.frame_phase * SCANLINE_PHASE_SHIFT;
+        self.frame_phase = (self.frame_phase + 1) % NUM_FRAME_PHASES;
+
+        for y in 0..frame.height() {
+            let line_phase = phase + y * SCANLINE_PHASE_SHIFT;
+            self.decode_line(frame, y, line_phase, width, false);
+            if self.settings.merge_fields {
+                self.decode_line(frame, y, line_phase + SCANLINE_PHASE_SHIFT, width, true);
+            }
+
+            for x in 0..width {
+                let (luma, u, v) = self.line_yuv[x];
+                let rgb = if self.settings.merge_fields {
+                    self.yuv_to_rgb(luma / 2.0, u / 2.0, v / 2.0)
+                } else {
+                    self.yuv_to_rgb(luma, u, v)
+                };
+                output.set_pixel(x, y, rgb);
+            }
+        }
+    }
+
+    // Generates the signal for a scanline and decodes it into one YUV value
+    // per output pixel, optionally accumulating onto the previous result.
+    fn decode_line(&mut self, frame: &FrameBuffer, y: usize, phase: usize, width: usize, accumulate: bool) {
+        let num_samples = frame.width() * SAMPLES_PER_PIXEL;
+
+        let artifacts = 1.0 + self.settings.artifacts;
+        let fringing = 1.0 + self.settings.fringing;
+
+        self.luma_sums.clear();
+        self.u_sums.clear();
+        self.v_sums.clear();
+        let (mut luma_sum, mut u_sum, mut v_sum) = (0.0, 0.0, 0.0);
+        self.luma_sums.push(luma_sum);
+        self.u_sums.push(u_sum);
+        self.v_sums.push(v_sum);
+
+        for x in 0..frame.width() {
+            let colour = frame.get_pixel(x, y) as usize % NUM_ENTRIES;
+            let luma = self.luma[colour];
+            for s in 0..SAMPLES_PER_PIXEL {
+                let sample_phase = (phase + x * SAMPLES_PER_PIXEL + s) % SAMPLES_PER_CYCLE;
+                let chroma = self.signal[colour][sample_phase] - luma;
+
+                luma_sum += luma + artifacts * chroma;
+
+                let signal = chroma + fringing * luma;
+                let (cos, sin) = self.carrier[sample_phase];
+                u_sum += signal * cos;
+                v_sum -= signal * sin;
+
+                self.luma_sums.push(luma_sum);
+                self.u_sums.push(u_sum);
+                self.v_sums.push(v_sum);
+            }
+        }
+
+        if !accumulate {
+            self.line_yuv.clear();
+            self.line_yuv.resize(width, (0.0, 0.0, 0.0));
+        }
+
+        // the sum of a window of samples centred on a sample, cut off at the
+        // ends of the line
+        let window = |sums: &[f32], centre: isize, length: usize| {
+            let start = centre - (length / 2) as isize;
+            let end = (start + length as isize).min(num_samples as isize).max(0) as usize;
+            let start = start.max(0) as usize;
+            if end > start { sums[end] - sums[start] } else { 0.0 }
+        };
+
+        for x in 0..width {
+            let centre = ((x as f32 + 0.5) * num_samples as f32 / width as f32) as isize;
+
+            let luma = window(&self.luma_sums, centre, self.luma_window) / self.luma_window as f32;
+
+            // demodulating a full-scale square wave yields half its amplitude
+            let u = window(&self.u_sums, centre, self.chroma_window) * 2.0 / self.chroma_window as f32;
+            let v = window(&self.v_sums, centre, self.chroma_window) * 2.0 / self.chroma_window as f32;
+
+            let yuv = &mut self.line_yuv[x];
+            yuv.0 += luma;
+            yuv.1 += u;
+            yuv.2 += v;
+        }
+    }
+
+    fn yuv_to_rgb(&self, luma: f32, u: f32, v: f32) -> (u8, u8, u8) {
+        let saturation = 1.0 + self.settings.saturation;
+        let contrast = 1.0 + self.settings.contrast;
+        let gamma = 1.0 + self.settings.gamma * 0.5;
+
+        let luma = luma * contrast + self.settings.brightness * 0.5;
+        let u = u * saturation * contrast;
+        let v = v * saturation * contrast;
+
+        let r = luma + 1.140 * v;
+        let g = luma - 0.395 * u - 0.581 * v;
+        let b = luma + 2.032 * u;
+
+        (Self::to_byte(r, gamma), Self::to_byte(g, gamma), Self::to_byte(b, gamma))
+    }
+
+    fn to_byte(value: f32, gamma: f32) -> u8 {
+        let value = value.max(0.0).min(1.0).powf(gamma);
+        (value * 255.0).round() as u8
+    }
+}
@@ -2,7 +2,8 @@ use std::fmt;
 
 use addressable::{PpuAddressable, Address, Result, Error, AddressDiff};
 use cpu::InterruptState;
-use renderer::Frame;
+use renderer::{Frame, Colour, COLOUR_MASK, EMPHASIS_SHIFT};
+use tile_cache::TileCache;
 
 const CONTROLLER: Address = 0;
 const MASK: Address = 1;
@@ -32,6 +33,9 @@ const MASK_EMPHASIZE_RED: u8 = bit!(5);
 const MASK_EMPHASIZE_GREEN: u8 = bit!(6);
 const MASK_EMPHASIZE_BLUE: u8 = bit!(7);
 
+const MASK_GREYSCALE_COLOUR_MASK: u8 = 0x30;
+const MASK_EMPHASIS_SHIFT: usize = 5;
+
 const STATUS_LAST_WRITE_MASK: u8 = mask!(5);
 const STATUS_SPRITE_OVERFLOW: u8 = bit!(5);
 const STATUS_SPRITE_0_HIT: u8 = bit!(6);
@@ -45,6 +49,10 @@ pub const DISPLAY_WIDTH: usize = 256;
 pub const DISPLAY_HEIGHT: usize = 240;
 pub const NUM_PIXELS: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
 
+// all four logical nametables laid out in a 2x2 grid
+pub const NAMETABLE_VIEW_WIDTH: usize = DISPLAY_WIDTH * 2;
+pub const NAMETABLE_VIEW_HEIGHT: usize = DISPLAY_HEIGHT * 2;
+
 pub const WIDTH_TILES: AddressDiff = 32;
 pub const HEIGHT_TILES: AddressDiff = 30;
 pub const TILE_WIDTH: AddressDiff = 8;
@@ -59,6 +67,7 @@ pub const SPRITE_PALETTE_BASE: Address = 0x3f10;
 
 pub const SPRITE_STRIDE: usize = 4;
 pub const NUM_SPRITES: usize = 64;
+pub const SPRITES_PER_LINE: usize = 8;
 
 const SPRITE_ATTRIBUTE_PALETTE_MASK: u8 = mask!(2);
 const SPRITE_ATTRIBUTE_PRIORITY: u8 = bit!(5);
@@ -81,15 +90,15 @@ pub struct PpuRegisterFile {
     address: u8,
 }
 
-#[derive(Debug)]
-struct Sprite {
-    x: u8,
-    y: u8,
-    index: u8,
-    palette: u8,
-    priority: bool,
-    horizontal_flip: bool,
-    vertical_flip: bool,
+#[derive(Debug, Clone, Copy)]
+pub struct Sprite {
+    pub x: u8,
+    pub y: u8,
+    pub index: u8,
+    pub palette: u8,
+    pub priority: bool,
+    pub horizontal_flip: bool,
+    pub vertical_flip: bool,
 }
 
 impl Sprite {
@@ -105,7 +114,7 @@ impl Sprite {
         }
     }
 
-    fn is_visible(&self) -> bool {
+    pub fn is_visible(&self) -> bool {
         self.y < 0xef
     }
 }
@@ -141,6 +150,7 @@ pub struct Ppu {
     address: Address,
     oam: Vec<u8>,
     data_latch: u8,
+    tile_cache: TileCache,
 }
 
 impl fmt::Display for Ppu {
@@ -176,6 +186,7 @@ impl Ppu {
             address: 0,
             oam: vec![0; OAM_SIZE],
             data_latch: 0,
+            tile_cache: TileCache::new(),
         }
     }
 
@@ -274,6 +285,7 @@ impl Ppu {
             }
             DATA => {
                 try!(memory.ppu_write8(self.address, data));
+                self.tile_cache.invalidate(self.address);
                 self.increment_address();
             }
             _ => return Err(Error::UnimplementedWrite(address)),
@@ -289,7 +301,7 @@ impl Ppu {
         }
     }
 
-    fn background_top_left_coord(&self) -> (AddressDiff, AddressDiff) {
+    pub fn background_top_left_coord(&self) -> (AddressDiff, AddressDiff) {
         let mut x = self.scroll_x as AddressDiff;
         let mut y = self.scroll_y as AddressDiff;
 
@@ -303,6 +315,22 @@ impl Ppu {
         (x, y)
     }
 
+    pub fn sprite(&self, i: usize) -> Sprite {
+        let index = i * SPRITE_STRIDE;
+        Sprite::new(self.oam[index + 3],
+                    self.oam[index + 0],
+                    self.oam[index + 2],
+                    self.oam[index + 1])
+    }
+
+    pub fn sprite_height(&self) -> usize {
+        if self.registers.controller & CONTROLLER_SPRITE_SIZE == 0 {
+            TILE_HEIGHT as usize
+        } else {
+            TILE_HEIGHT as usize * 2
+        }
+    }
+
     fn sprite_base_patterntable_address(&self) -> Address {
         if self.registers.controller & CONTROLLER_SPRITE_PATTERN_TABLE_8X8 == 0 {
             0x0000
@@ -312,6 +340,17 @@ impl Ppu {
     }
 
 
+    // Applies greyscale and colour emphasis from PPUMASK to a palette entry
+    fn output_colour(&self, palette_entry: u8) -> Colour {
+        let mut colour = palette_entry as Colour & COLOUR_MASK;
+        if self.registers.mask & MASK_GREYSCALE != 0 {
+            colour &= MASK_GREYSCALE_COLOUR_MASK as Colour;
+        }
+        let emphasis = (self.registers.mask >> MASK_EMPHASIS_SHIFT) as Colour;
+
+        colour | (emphasis << EMPHASIS_SHIFT)
+    }
+
     fn metatile_id(tile_x: AddressDiff, tile_y: AddressDiff) -> u8 {
         // a metatile is 2x2 tiles
         let x = tile_x / 2;
@@ -321,6 +360,23 @@ impl Ppu {
         (((y & bit!(0)) << 1) | (x & bit!(0))) as u8
     }
 
+    // Notifies the PPU of a pattern table write made without going through
+    // PPUDATA, so that its cached copy of the tile is discarded
+    pub fn pattern_table_written(&mut self, address: Address) {
+        self.tile_cache.invalidate(address);
+    }
+
+    // Resolves the 4 entries of a palette to output colours. Entry 0 is the
+    // universal background colour.
+    fn palette_colours<M: PpuAddressable>(&self, memory: &mut M, palette_base: Address) -> Result<[Colour; 4]> {
+        let mut colours = [0; 4];
+        colours[0] = self.output_colour(try!(memory.ppu_read8(UNIVERSAL_BACKGROUND_COLOUR)));
+        for i in 1..colours.len() {
+            colours[i] = self.output_colour(try!(memory.ppu_read8(palette_base + i as AddressDiff)));
+        }
+        Ok(colours)
+    }
+
     fn render_background_tile<F: Frame, M: PpuAddressable>(&mut self,
                                                            frame: &mut F,
                                                            memory: &mut M,
@@ -329,7 +385,9 @@ impl Ppu {
                                                            nt_tile_x: AddressDiff,
                                                            nt_tile_y: AddressDiff,
                                                            px_off_x: isize,
-                                                           px_off_y: isize) -> Result<()> {
+                                                           px_off_y: isize,
+                                                           width: usize,
+                                                           height: usize) -> Result<()> {
 
         let nt_offset = nt_tile_y * WIDTH_TILES + nt_tile_x;
         let nt_address = nt_base + nt_offset;
@@ -346,44 +404,47 @@ impl Ppu {
         let at_bits = (at_byte >> (Self::metatile_id(nt_tile_x, nt_tile_y) * 2)) & mask!(2);
 
         let palette_base = BACKGROUND_PALETTE_BASE + (at_bits as AddressDiff * PALETTE_STRIDE);
+        let colours = try!(self.palette_colours(memory, palette_base));
+
+        let tile = try!(self.tile_cache.tile(memory, pt_address));
+
+        // clip the tile's columns to the frame
+        let first_column = (-px_off_x).max(0) as usize;
+        let last_column = (width as isize - px_off_x).min(TILE_WIDTH as isize);
+        if last_column <= first_column as isize {
+            return Ok(());
+        }
+        let last_column = last_column as usize;
 
-        for i in 0..TILE_HEIGHT {
-            let mut row_0 = try!(memory.ppu_read8(pt_address + i));
-            let mut row_1 = try!(memory.ppu_read8(pt_address + TILE_HEIGHT + i));
+        let mut row_colours = [0; TILE_WIDTH as usize];
 
+        for (i, row) in tile.iter().enumerate() {
             let pixel_y = px_off_y + i as isize;
 
-            if pixel_y < 0 || pixel_y >= DISPLAY_HEIGHT as isize {
+            if pixel_y < 0 || pixel_y >= height as isize {
                 continue;
             }
 
-            for j in 0..TILE_WIDTH {
-                let palette_index = (row_0 & bit!(0)) | ((row_1 & bit!(0)) << 1);
-                row_0 >>= 1;
-                row_1 >>= 1;
-
-                if palette_index != 0 {
-                    let palette_address = palette_base + palette_index as AddressDiff;
-                    let colour = try!(memory.ppu_read8(palette_address));
-
-                    let pixel_x_offset = (TILE_WIDTH - 1 - j) as isize;
-                    let pixel_x = px_off_x + pixel_x_offset;
-
-                    if pixel_x >= 0 && pixel_x < DISPLAY_WIDTH as isize {
-                        frame.set_pixel(pixel_x as usize, pixel_y as usize, colour);
-                    }
-                }
+            for (colour, &palette_index) in row_colours.iter_mut().zip(row.iter()) {
+                *colour = colours[palette_index as usize];
             }
+
+            frame.set_row((px_off_x + first_column as isize) as usize,
+                          pixel_y as usize,
+                          &row_colours[first_column..last_column]);
         }
         Ok(())
     }
 
-    fn render_universal_background<F: Frame, M: PpuAddressable>(&mut self, frame: &mut F, memory: &mut M) -> Result<()> {
-        let colour = try!(memory.ppu_read8(UNIVERSAL_BACKGROUND_COLOUR));
-        for i in 0..DISPLAY_HEIGHT {
-            for j in 0..DISPLAY_WIDTH {
-                frame.set_pixel(j, i, colour);
-            }
+    fn render_universal_background<F: Frame, M: PpuAddressable>(&mut self,
+                                                                 frame: &mut F,
+                                                                 memory: &mut M,
+                                                                 width: usize,
+                                                                 height: usize) -> Result<()> {
+        let colour = self.output_colour(try!(memory.ppu_read8(UNIVERSAL_BACKGROUND_COLOUR)));
+        let row = vec![colour; width];
+        for i in 0..height {
+            frame.set_row(0, i, &row);
         }
         Ok(())
     }
@@ -430,7 +491,8 @@ impl Ppu {
                 let px_y = (i * TILE_HEIGHT) as isize - pixel_offset_y;
 
                 try!(self.render_background_tile(frame, memory, pt_base, nametable_address,
-                                            local_x, local_y, px_x, px_y));
+                                            local_x, local_y, px_x, px_y,
+                                            DISPLAY_WIDTH, DISPLAY_HEIGHT));
             }
         }
 
@@ -438,41 +500,62 @@ impl Ppu {
     }
 
     fn render_sprite_8x8<F: Frame, M: PpuAddressable>(&mut self, frame: &mut F, memory: &mut M, sprite: Sprite) -> Result<bool> {
+        let pt_base = self.sprite_base_patterntable_address();
+        let pt_offset = sprite.index as AddressDiff * PATTERN_TABLE_ENTRY_BYTES;
+
+        self.render_sprite_tile(frame, memory, sprite, pt_base | pt_offset, 0, TILE_HEIGHT)
+    }
+
+    // 8x16 sprites take their pattern table from bit 0 of the tile index,
+    // and are the even tile above the odd one after it
+    fn render_sprite_8x16<F: Frame, M: PpuAddressable>(&mut self, frame: &mut F, memory: &mut M, sprite: Sprite) -> Result<bool> {
+        let pt_base = if sprite.index & bit!(0) == 0 { 0x0000 } else { 0x1000 };
+        let top_index = (sprite.index & !bit!(0)) as AddressDiff;
+        let height = TILE_HEIGHT * 2;
 
         let mut hit = false;
+        for half in 0..2 {
+            let pt_address = pt_base | ((top_index + half) * PATTERN_TABLE_ENTRY_BYTES);
+            hit |= try!(self.render_sprite_tile(frame, memory, sprite, pt_address, half * TILE_HEIGHT, height));
+        }
+        Ok(hit)
+    }
 
-        let pt_base = self.sprite_base_patterntable_address();
-        let pt_offset = sprite.index as AddressDiff * PATTERN_TABLE_ENTRY_BYTES;
-        let pt_address = pt_base | pt_offset;
+    // Draws the sprite's tile at the given pattern address, `offset` rows
+    // down a sprite `height` rows tall, which it's flipped within
+    fn render_sprite_tile<F: Frame, M: PpuAddressable>(&mut self,
+                                                       frame: &mut F,
+                                                       memory: &mut M,
+                                                       sprite: Sprite,
+                                                       pt_address: Address,
+                                                       offset: AddressDiff,
+                                                       height: AddressDiff) -> Result<bool> {
+        let mut hit = false;
 
         let palette_base = SPRITE_PALETTE_BASE + sprite.palette as AddressDiff * PALETTE_STRIDE;
+        let colours = try!(self.palette_colours(memory, palette_base));
 
-        for i in 0..TILE_HEIGHT {
-            let mut row_0 = try!(memory.ppu_read8(pt_address + i));
-            let mut row_1 = try!(memory.ppu_read8(pt_address + TILE_HEIGHT + i));
+        let tile = try!(self.tile_cache.tile(memory, pt_address));
 
+        for (i, row) in tile.iter().enumerate() {
+            let i = offset + i as AddressDiff;
             let pixel_y = if sprite.vertical_flip {
-                sprite.y as AddressDiff + TILE_HEIGHT - 1 - i
+                sprite.y as AddressDiff + height - 1 - i
             } else {
                 sprite.y as AddressDiff + i
             };
 
-            for j in 0..TILE_WIDTH {
-                let palette_index = (row_0 & bit!(0)) | ((row_1 & bit!(0)) << 1);
-                row_0 >>= 1;
-                row_1 >>= 1;
+            for (j, &palette_index) in row.iter().enumerate() {
+                let j = j as AddressDiff;
 
                 if palette_index != 0 {
-                    let palette_address = palette_base + palette_index as AddressDiff;
-                    let colour = try!(memory.ppu_read8(palette_address));
-
                     let pixel_x = if sprite.horizontal_flip {
-                        sprite.x as AddressDiff + j
-                    } else {
                         sprite.x as AddressDiff + TILE_WIDTH - 1 - j
+                    } else {
+                        sprite.x as AddressDiff + j
                     };
 
-                    frame.set_pixel(pixel_x as usize, pixel_y as usize, colour);
+                    frame.set_pixel(pixel_x as usize, pixel_y as usize, colours[palette_index as usize]);
                     hit = true;
                 }
             }
@@ -484,11 +567,7 @@ impl Ppu {
     fn render_sprites_8x8<F: Frame, M: PpuAddressable>(&mut self, frame: &mut F, memory: &mut M) -> Result<()> {
 
         for i in 0..NUM_SPRITES {
-            let index = i * SPRITE_STRIDE;
-            let sprite = Sprite::new(self.oam[index + 3],
-                                     self.oam[index + 0],
-                                     self.oam[index + 2],
-                                     self.oam[index + 1]);
+            let sprite = self.sprite(i);
 
             if sprite.is_visible() {
                 let hit = try!(self.render_sprite_8x8(frame, memory, sprite));
@@ -501,10 +580,61 @@ impl Ppu {
         Ok(())
     }
 
+    // Renders a single sprite with its top-left corner at the given position,
+    // without affecting sprite 0 hit.
+    pub fn render_sprite_graphic<F: Frame, M: PpuAddressable>(&mut self,
+                                                              frame: &mut F,
+                                                              memory: &mut M,
+                                                              i: usize,
+                                                              x: u8,
+                                                              y: u8) -> Result<()> {
+        let mut sprite = self.sprite(i);
+        sprite.x = x;
+        sprite.y = y;
+
+        self.tile_cache.sync(memory);
+
+        if self.sprite_height() == TILE_HEIGHT as usize * 2 {
+            try!(self.render_sprite_8x16(frame, memory, sprite));
+        } else {
+            try!(self.render_sprite_8x8(frame, memory, sprite));
+        }
+
+        Ok(())
+    }
+
     pub fn render<F: Frame, M: PpuAddressable>(&mut self, frame: &mut F, memory: &mut M) -> Result<()> {
-        try!(self.render_universal_background(frame, memory));
+        self.tile_cache.sync(memory);
+        try!(self.render_universal_background(frame, memory, DISPLAY_WIDTH, DISPLAY_HEIGHT));
         try!(self.render_background(frame, memory));
         try!(self.render_sprites_8x8(frame, memory));
         Ok(())
     }
+
+    // Renders the background of all four logical nametables into a
+    // NAMETABLE_VIEW_WIDTH x NAMETABLE_VIEW_HEIGHT frame, ignoring scroll.
+    pub fn render_nametables<F: Frame, M: PpuAddressable>(&mut self, frame: &mut F, memory: &mut M) -> Result<()> {
+        self.tile_cache.sync(memory);
+        try!(self.render_universal_background(frame, memory, NAMETABLE_VIEW_WIDTH, NAMETABLE_VIEW_HEIGHT));
+
+        let pt_base = self.background_base_patterntable_address();
+
+        for i in 0..(HEIGHT_TILES * 2) {
+            for j in 0..(WIDTH_TILES * 2) {
+                let nametable_address = self.tile_coord_to_nametable_base(j, i);
+
+                let local_x = j % WIDTH_TILES;
+                let local_y = i % HEIGHT_TILES;
+
+                let px_x = (j * TILE_WIDTH) as isize;
+                let px_y = (i * TILE_HEIGHT) as isize;
+
+                try!(self.render_background_tile(frame, memory, pt_base, nametable_address,
+                                                 local_x, local_y, px_x, px_y,
+                                                 NAMETABLE_VIEW_WIDTH, NAMETABLE_VIEW_HEIGHT));
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -55,4 +55,8 @@ impl<'a, C: 'a + Cartridge> PpuAddressable for PpuMemoryLayout<'a, C> {
             _ => Err(Error::BusErrorWrite(address)),
         }
     }
+
+    fn pattern_table_generation(&self) -> usize {
+        self.cartridge.pattern_table_generation()
+    }
 }
@@ -0,0 +1,173 @@
+use envelope::Envelope;
+use length_counter::LengthCounter;
+
+const CONTROL: usize = 0;
+const SWEEP: usize = 1;
+const TIMER_LOW: usize = 2;
+const LENGTH_TIMER_HIGH: usize = 3;
+
+const CONTROL_DUTY_SHIFT: usize = 6;
+const CONTROL_LENGTH_HALT: u8 = bit!(5);
+
+const SWEEP_ENABLED: u8 = bit!(7);
+const SWEEP_PERIOD_SHIFT: usize = 4;
+const SWEEP_PERIOD_MASK: u8 = mask!(3);
+const SWEEP_NEGATE: u8 = bit!(3);
+const SWEEP_SHIFT_MASK: u8 = mask!(3);
+
+const TIMER_HIGH_MASK: u8 = mask!(3);
+const LENGTH_INDEX_SHIFT: usize = 3;
+
+// Periods outside this range silence the channel
+const MIN_PERIOD: u16 = 8;
+const MAX_TARGET_PERIOD: u16 = 0x7ff;
+
+const SEQUENCE_LENGTH: usize = 8;
+
+const DUTY_SEQUENCES: [[u8; SEQUENCE_LENGTH]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+// The two pulse channels differ only in how their sweep units negate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PulseChannel {
+    // subtracts the change and one more (ones' complement)
+    One,
+    // subtracts the change (two's complement)
+    Two,
+}
+
+pub struct Pulse {
+    channel: PulseChannel,
+    duty: usize,
+    sequence_position: usize,
+    timer_period: u16,
+    timer: u16,
+    envelope: Envelope,
+    length_counter: LengthCounter,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_reload: bool,
+    sweep_divider: u8,
+}
+
+impl Pulse {
+    pub fn new(channel: PulseChannel) -> Self {
+        Pulse {
+            channel: channel,
+            duty: 0,
+            sequence_position: 0,
+            timer_period: 0,
+            timer: 0,
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_reload: false,
+            sweep_divider: 0,
+        }
+    }
+
+    // Writes one of the channel's 4 registers
+    pub fn write(&mut self, register: usize, data: u8) {
+        match register {
+            CONTROL => {
+                self.duty = (data >> CONTROL_DUTY_SHIFT) as usize;
+                self.length_counter.set_halt(data & CONTROL_LENGTH_HALT != 0);
+                self.envelope.write(data);
+            }
+            SWEEP => {
+                self.sweep_enabled = data & SWEEP_ENABLED != 0;
+                self.sweep_period = (data >> SWEEP_PERIOD_SHIFT) & SWEEP_PERIOD_MASK;
+                self.sweep_negate = data & SWEEP_NEGATE != 0;
+                self.sweep_shift = data & SWEEP_SHIFT_MASK;
+                self.sweep_reload = true;
+            }
+            TIMER_LOW => {
+                self.timer_period = (self.timer_period & 0xff00) | data as u16;
+            }
+            LENGTH_TIMER_HIGH => {
+                self.timer_period = (self.timer_period & 0x00ff) | (((data & TIMER_HIGH_MASK) as u16) << 8);
+                self.length_counter.load(data >> LENGTH_INDEX_SHIFT);
+                self.sequence_position = 0;
+                self.envelope.restart();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn active(&self) -> bool {
+        self.length_counter.active()
+    }
+
+    // Clocked every APU cycle, i.e. every other CPU cycle
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_position = (self.sequence_position + 1) % SEQUENCE_LENGTH;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+        self.clock_sweep();
+    }
+
+    fn target_period(&self) -> u16 {
+        let change = self.timer_period >> self.sweep_shift;
+        if self.sweep_negate {
+            let change = match self.channel {
+                PulseChannel::One => change + 1,
+                PulseChannel::Two => change,
+            };
+            self.timer_period.saturating_sub(change)
+        } else {
+            self.timer_period + change
+        }
+    }
+
+    // The sweep unit mutes the channel whether or not it's enabled
+    fn sweep_muted(&self) -> bool {
+        self.timer_period < MIN_PERIOD || self.target_period() > MAX_TARGET_PERIOD
+    }
+
+    fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.sweep_muted() {
+            self.timer_period = self.target_period();
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    // Current output level, from 0 to 15
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.active() || self.sweep_muted() ||
+           DUTY_SEQUENCES[self.duty][self.sequence_position] == 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
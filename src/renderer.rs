@@ -1,3 +1,19 @@
+// A 6-bit NES colour in the low bits, with the 3 PPUMASK colour
+// emphasis bits (red, green, blue) above it.
+pub type Colour = u16;
+
+pub const COLOUR_MASK: Colour = mask!(6);
+pub const EMPHASIS_SHIFT: usize = 6;
+
 pub trait Frame {
-    fn set_pixel(&mut self, x: usize, y: usize, colour: u8);
+    fn set_pixel(&mut self, x: usize, y: usize, colour: Colour);
+
+    fn get_pixel(&self, x: usize, y: usize) -> Colour;
+
+    // Sets a horizontal run of pixels starting at (x, y)
+    fn set_row(&mut self, x: usize, y: usize, colours: &[Colour]) {
+        for (i, &colour) in colours.iter().enumerate() {
+            self.set_pixel(x + i, y, colour);
+        }
+    }
 }
@@ -0,0 +1,173 @@
+use std::{fs, io, result};
+use std::io::Read;
+
+use renderer::Colour;
+use frame_buffer::{FrameBuffer, RgbBuffer};
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidSize(usize),
+    IoError(io::Error),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+pub const NUM_COLOURS: usize = 64;
+pub const NUM_EMPHASIS_COMBINATIONS: usize = 8;
+pub const NUM_ENTRIES: usize = NUM_COLOURS * NUM_EMPHASIS_COMBINATIONS;
+
+const BYTES_PER_COLOUR: usize = 3;
+const BASIC_FILE_NUM_BYTES: usize = NUM_COLOURS * BYTES_PER_COLOUR;
+const EMPHASIS_FILE_NUM_BYTES: usize = NUM_ENTRIES * BYTES_PER_COLOUR;
+
+const EMPHASIS_RED: usize = bit!(0);
+const EMPHASIS_GREEN: usize = bit!(1);
+const EMPHASIS_BLUE: usize = bit!(2);
+
+// Colours $xE and $xF are blacker than black and unaffected by emphasis
+const EMPHASIS_UNAFFECTED_COLUMN_MASK: usize = 0x0e;
+
+// Approximate attenuation applied to the channels which aren't emphasized,
+// used when a palette file doesn't contain its own emphasis colours.
+const EMPHASIS_ATTENUATION: f32 = 0.746;
+
+pub const DEFAULT: &'static str = "2c02";
+
+pub struct RgbPalette {
+    colours: Vec<(u8, u8, u8)>,
+}
+
+impl RgbPalette {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        match data.len() {
+            BASIC_FILE_NUM_BYTES => Ok(Self::with_generated_emphasis(&Self::colours_from_bytes(data))),
+            EMPHASIS_FILE_NUM_BYTES => Ok(RgbPalette { colours: Self::colours_from_bytes(data) }),
+            other => Err(Error::InvalidSize(other)),
+        }
+    }
+
+    pub fn parse_file(mut file: fs::File) -> Result<Self> {
+        let mut buffer = Vec::new();
+
+        if let Err(e) = file.read_to_end(&mut buffer) {
+            return Err(Error::IoError(e));
+        }
+
+        Self::from_bytes(&buffer)
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        let colours: &[(u8, u8, u8)] = match name {
+            "2c02" => &PALETTE_2C02,
+            "2c03" => &PALETTE_2C03,
+            "fceux" => &PALETTE_FCEUX,
+            _ => return None,
+        };
+
+        Some(Self::with_generated_emphasis(colours))
+    }
+
+    pub fn rgb(&self, colour: Colour) -> (u8, u8, u8) {
+        self.colours[colour as usize % NUM_ENTRIES]
+    }
+
+    pub fn apply(&self, frame: &FrameBuffer, output: &mut RgbBuffer) {
+        output.resize(frame.width(), frame.height());
+        for y in 0..frame.height() {
+            for x in 0..frame.width() {
+                output.set_pixel(x, y, self.rgb(frame.get_pixel(x, y)));
+            }
+        }
+    }
+
+    fn colours_from_bytes(data: &[u8]) -> Vec<(u8, u8, u8)> {
+        data.chunks(BYTES_PER_COLOUR)
+            .map(|c| (c[0], c[1], c[2]))
+            .collect()
+    }
+
+    fn with_generated_emphasis(base: &[(u8, u8, u8)]) -> Self {
+        let mut colours = Vec::with_capacity(NUM_ENTRIES);
+
+        for emphasis in 0..NUM_EMPHASIS_COMBINATIONS {
+            for (i, &(r, g, b)) in base.iter().enumerate() {
+                if emphasis == 0 || i & EMPHASIS_UNAFFECTED_COLUMN_MASK == EMPHASIS_UNAFFECTED_COLUMN_MASK {
+                    colours.push((r, g, b));
+                    continue;
+                }
+
+                let attenuate = |value: u8, channel: usize| {
+                    if emphasis & channel == 0 {
+                        (value as f32 * EMPHASIS_ATTENUATION) as u8
+                    } else {
+                        value
+                    }
+                };
+
+                colours.push((attenuate(r, EMPHASIS_RED),
+                              attenuate(g, EMPHASIS_GREEN),
+                              attenuate(b, EMPHASIS_BLUE)));
+            }
+        }
+
+        RgbPalette { colours: colours }
+    }
+}
+
+const PALETTE_2C02: [(u8, u8, u8); NUM_COLOURS] = [
+    (124, 124, 124), (0, 0, 252), (0, 0, 118), (68, 40, 188),
+    (140, 0, 32), (168, 16, 0), (168, 0, 16), (136, 20, 0),
+    (80, 48, 0), (0, 120, 0), (0, 104, 0), (0, 88, 0),
+    (0, 64, 88), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+    (188, 188, 188), (0, 120, 248), (0, 88, 248), (104, 68, 252),
+    (216, 0, 204), (228, 0, 88), (248, 56, 0), (228, 92, 16),
+    (172, 124, 0), (0, 184, 0), (0, 168, 0), (0, 168, 68),
+    (0, 136, 136), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+    (248, 248, 248), (60, 188, 252), (104, 136, 252), (152, 120, 248),
+    (248, 120, 248), (248, 88, 152), (248, 120, 88), (252, 160, 68),
+    (248, 184, 0), (184, 248, 24), (88, 216, 84), (88, 248, 152),
+    (0, 232, 216), (120, 120, 120), (0, 0, 0), (0, 0, 0),
+    (252, 252, 252), (164, 228, 252), (184, 184, 248), (216, 184, 248),
+    (248, 184, 248), (248, 164, 192), (240, 208, 176), (252, 224, 168),
+    (248, 216, 120), (216, 248, 120), (184, 248, 184), (184, 248, 216),
+    (0, 252, 252), (216, 216, 216), (0, 0, 0), (0, 0, 0),
+];
+
+// The RGB PPU has 3 bits per channel, scaled here to 8 bits
+const PALETTE_2C03: [(u8, u8, u8); NUM_COLOURS] = [
+    (109, 109, 109), (0, 36, 146), (0, 0, 219), (109, 73, 219),
+    (146, 0, 109), (182, 0, 109), (182, 36, 0), (146, 73, 0),
+    (109, 73, 0), (36, 73, 0), (0, 109, 36), (0, 146, 0),
+    (0, 73, 73), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+    (182, 182, 182), (0, 109, 219), (0, 73, 255), (146, 0, 255),
+    (182, 0, 255), (255, 0, 146), (255, 0, 0), (219, 109, 0),
+    (146, 109, 0), (36, 146, 0), (0, 146, 0), (0, 182, 109),
+    (0, 146, 146), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+    (255, 255, 255), (109, 182, 255), (146, 146, 255), (219, 109, 255),
+    (255, 0, 255), (255, 109, 255), (255, 146, 0), (255, 182, 0),
+    (219, 219, 0), (109, 219, 0), (0, 255, 0), (73, 255, 219),
+    (0, 255, 255), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+    (255, 255, 255), (182, 219, 255), (219, 182, 255), (255, 182, 255),
+    (255, 146, 255), (255, 182, 182), (255, 219, 146), (255, 255, 73),
+    (255, 255, 109), (182, 255, 73), (146, 255, 109), (73, 255, 219),
+    (146, 219, 255), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+];
+
+const PALETTE_FCEUX: [(u8, u8, u8); NUM_COLOURS] = [
+    (0x74, 0x74, 0x74), (0x24, 0x18, 0x8c), (0x00, 0x00, 0xa8), (0x44, 0x00, 0x9c),
+    (0x8c, 0x00, 0x74), (0xa8, 0x00, 0x10), (0xa4, 0x00, 0x00), (0x7c, 0x08, 0x00),
+    (0x40, 0x2c, 0x00), (0x00, 0x44, 0x00), (0x00, 0x50, 0x00), (0x00, 0x3c, 0x14),
+    (0x18, 0x3c, 0x5c), (0x00, 0x00, 0x00), (0x00, 0x00, 0x00), (0x00, 0x00, 0x00),
+    (0xbc, 0xbc, 0xbc), (0x00, 0x70, 0xec), (0x20, 0x38, 0xec), (0x80, 0x00, 0xf0),
+    (0xbc, 0x00, 0xbc), (0xe4, 0x00, 0x58), (0xd8, 0x28, 0x00), (0xc8, 0x4c, 0x0c),
+    (0x88, 0x70, 0x00), (0x00, 0x94, 0x00), (0x00, 0xa8, 0x00), (0x00, 0x90, 0x38),
+    (0x00, 0x80, 0x88), (0x00, 0x00, 0x00), (0x00, 0x00, 0x00), (0x00, 0x00, 0x00),
+    (0xfc, 0xfc, 0xfc), (0x3c, 0xbc, 0xfc), (0x5c, 0x94, 0xfc), (0xcc, 0x88, 0xfc),
+    (0xf4, 0x78, 0xfc), (0xfc, 0x74, 0xb4), (0xfc, 0x74, 0x60), (0xfc, 0x98, 0x38),
+    (0xf0, 0xbc, 0x3c), (0x80, 0xd0, 0x10), (0x4c, 0xdc, 0x48), (0x58, 0xf8, 0x98),
+    (0x00, 0xe8, 0xd8), (0x78, 0x78, 0x78), (0x00, 0x00, 0x00), (0x00, 0x00, 0x00),
+    (0xfc, 0xfc, 0xfc), (0xa8, 0xe4, 0xfc), (0xc4, 0xd4, 0xfc), (0xd4, 0xc8, 0xfc),
+    (0xfc, 0xc4, 0xfc), (0xfc, 0xc4, 0xd8), (0xfc, 0xbc, 0xb0), (0xfc, 0xd8, 0xa8),
+    (0xfc, 0xe4, 0xa0), (0xe0, 0xfc, 0xa0), (0xa8, 0xf0, 0xbc), (0xb0, 0xfc, 0xcc),
+    (0x9c, 0xfc, 0xf0), (0xc4, 0xc4, 0xc4), (0x00, 0x00, 0x00), (0x00, 0x00, 0x00),
+];
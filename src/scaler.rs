@@ -0,0 +1,239 @@
+use frame_buffer::RgbBuffer;
+
+type Rgb = (u8, u8, u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scaler {
+    Nearest(usize),
+    Scale2x,
+    Scale3x,
+    Smooth2x,
+}
+
+impl Scaler {
+    pub fn from_name(name: &str, scale: usize) -> Option<Self> {
+        match name {
+            "nearest" => Some(Scaler::Nearest(scale)),
+            "scale2x" => Some(Scaler::Scale2x),
+            "scale3x" => Some(Scaler::Scale3x),
+            "smooth2x" => Some(Scaler::Smooth2x),
+            _ => None,
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        match *self {
+            Scaler::Nearest(scale) => scale,
+            Scaler::Scale2x => 2,
+            Scaler::Scale3x => 3,
+            Scaler::Smooth2x => 2,
+        }
+    }
+}
+
+// Software post-processing applied to the RGB picture before presentation
+#[derive(Debug, Clone, Copy)]
+pub struct PostProcess {
+    pub scaler: Scaler,
+    // brightness of the dark rows between scanlines, from 0 to 1
+    pub scanlines: Option<f32>,
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        PostProcess {
+            scaler: Scaler::Nearest(1),
+            scanlines: None,
+        }
+    }
+
+    pub fn apply(&self, input: &RgbBuffer, output: &mut RgbBuffer) {
+        let factor = self.scaler.factor();
+        output.resize(input.width() * factor, input.height() * factor);
+
+        match self.scaler {
+            Scaler::Nearest(scale) => nearest(input, output, scale),
+            Scaler::Scale2x => scale2x(input, output),
+            Scaler::Scale3x => scale3x(input, output),
+            Scaler::Smooth2x => smooth2x(input, output),
+        }
+
+        if let Some(brightness) = self.scanlines {
+            // at the source size there are no rows between lines to darken
+            if factor > 1 {
+                scanlines(output, factor, brightness);
+            }
+        }
+    }
+}
+
+// Returns the pixel at the given offset from (x, y), clamping to the edges
+fn neighbour(input: &RgbBuffer, x: usize, y: usize, dx: isize, dy: isize) -> Rgb {
+    let nx = (x as isize + dx).max(0).min(input.width() as isize - 1) as usize;
+    let ny = (y as isize + dy).max(0).min(input.height() as isize - 1) as usize;
+    input.get_pixel(nx, ny)
+}
+
+fn nearest(input: &RgbBuffer, output: &mut RgbBuffer, scale: usize) {
+    for y in 0..output.height() {
+        for x in 0..output.width() {
+            output.set_pixel(x, y, input.get_pixel(x / scale, y / scale));
+        }
+    }
+}
+
+// AdvanceMAME Scale2x, on the neighbourhood:
+// A B C
+// D E F
+// G H I
+fn scale2x(input: &RgbBuffer, output: &mut RgbBuffer) {
+    for y in 0..input.height() {
+        for x in 0..input.width() {
+            let b = neighbour(input, x, y, 0, -1);
+            let d = neighbour(input, x, y, -1, 0);
+            let e = input.get_pixel(x, y);
+            let f = neighbour(input, x, y, 1, 0);
+            let h = neighbour(input, x, y, 0, 1);
+
+            let (e0, e1, e2, e3) = if b != h && d != f {
+                (if d == b { d } else { e },
+                 if b == f { f } else { e },
+                 if d == h { d } else { e },
+                 if h == f { f } else { e })
+            } else {
+                (e, e, e, e)
+            };
+
+            output.set_pixel(x * 2, y * 2, e0);
+            output.set_pixel(x * 2 + 1, y * 2, e1);
+            output.set_pixel(x * 2, y * 2 + 1, e2);
+            output.set_pixel(x * 2 + 1, y * 2 + 1, e3);
+        }
+    }
+}
+
+fn scale3x(input: &RgbBuffer, output: &mut RgbBuffer) {
+    for y in 0..input.height() {
+        for x in 0..input.width() {
+            let a = neighbour(input, x, y, -1, -1);
+            let b = neighbour(input, x, y, 0, -1);
+            let c = neighbour(input, x, y, 1, -1);
+            let d = neighbour(input, x, y, -1, 0);
+            let e = input.get_pixel(x, y);
+            let f = neighbour(input, x, y, 1, 0);
+            let g = neighbour(input, x, y, -1, 1);
+            let h = neighbour(input, x, y, 0, 1);
+            let i = neighbour(input, x, y, 1, 1);
+
+            let mut out = [e; 9];
+            if b != h && d != f {
+                out[0] = if d == b { d } else { e };
+                out[1] = if (d == b && e != c) || (b == f && e != a) { b } else { e };
+                out[2] = if b == f { f } else { e };
+                out[3] = if (d == b && e != g) || (d == h && e != a) { d } else { e };
+                out[5] = if (b == f && e != i) || (h == f && e != c) { f } else { e };
+                out[6] = if d == h { d } else { e };
+                out[7] = if (d == h && e != i) || (h == f && e != g) { h } else { e };
+                out[8] = if h == f { f } else { e };
+            }
+
+            for (k, &pixel) in out.iter().enumerate() {
+                output.set_pixel(x * 3 + k % 3, y * 3 + k / 3, pixel);
+            }
+        }
+    }
+}
+
+// hqx considers two colours different if their YUV components differ by
+// more than these thresholds.
+const HQX_THRESHOLD_Y: i32 = 48;
+const HQX_THRESHOLD_U: i32 = 7;
+const HQX_THRESHOLD_V: i32 = 6;
+
+fn yuv((r, g, b): Rgb) -> (i32, i32, i32) {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    ((r + g + b) / 3, (r - b) / 4 + 128, (2 * g - r - b) / 8 + 128)
+}
+
+fn hqx_different(a: Rgb, b: Rgb) -> bool {
+    let (ya, ua, va) = yuv(a);
+    let (yb, ub, vb) = yuv(b);
+
+    (ya - yb).abs() > HQX_THRESHOLD_Y ||
+        (ua - ub).abs() > HQX_THRESHOLD_U ||
+        (va - vb).abs() > HQX_THRESHOLD_V
+}
+
+fn interpolate(colours: &[(Rgb, u32)]) -> Rgb {
+    let total: u32 = colours.iter().map(|&(_, w)| w).sum();
+    let mut sum = (0, 0, 0);
+    for &((r, g, b), w) in colours.iter() {
+        sum.0 += r as u32 * w;
+        sum.1 += g as u32 * w;
+        sum.2 += b as u32 * w;
+    }
+    ((sum.0 / total) as u8, (sum.1 / total) as u8, (sum.2 / total) as u8)
+}
+
+// Computes one output quadrant of smooth2x, given the centre pixel, the
+// diagonal neighbour in the quadrant's direction, and the two adjacent
+// neighbours that share an edge with the quadrant.
+fn smooth2x_quadrant(centre: Rgb, corner: Rgb, side_a: Rgb, side_b: Rgb) -> Rgb {
+    let edge_a = hqx_different(centre, side_a);
+    let edge_b = hqx_different(centre, side_b);
+    let sides_similar = !hqx_different(side_a, side_b);
+
+    if edge_a && edge_b && sides_similar {
+        // the centre pixel is cut off by a diagonal edge between the sides
+        if hqx_different(centre, corner) {
+            interpolate(&[(centre, 2), (side_a, 1), (side_b, 1)])
+        } else {
+            interpolate(&[(centre, 6), (side_a, 1), (side_b, 1)])
+        }
+    } else if edge_a && edge_b {
+        interpolate(&[(centre, 2), (side_a, 1), (side_b, 1)])
+    } else if hqx_different(centre, corner) {
+        if edge_a {
+            interpolate(&[(centre, 3), (side_a, 1)])
+        } else if edge_b {
+            interpolate(&[(centre, 3), (side_b, 1)])
+        } else {
+            interpolate(&[(centre, 3), (corner, 1)])
+        }
+    } else {
+        centre
+    }
+}
+
+// A smoothing 2x scaler in the spirit of hq2x: it uses hqx's YUV
+// similarity thresholds, but a few interpolation rules per output quadrant
+// rather than hq2x's 256-case pattern table, so it isn't hq2x's output.
+fn smooth2x(input: &RgbBuffer, output: &mut RgbBuffer) {
+    for y in 0..input.height() {
+        for x in 0..input.width() {
+            let n = |dx, dy| neighbour(input, x, y, dx, dy);
+            let e = input.get_pixel(x, y);
+
+            output.set_pixel(x * 2, y * 2, smooth2x_quadrant(e, n(-1, -1), n(0, -1), n(-1, 0)));
+            output.set_pixel(x * 2 + 1, y * 2, smooth2x_quadrant(e, n(1, -1), n(0, -1), n(1, 0)));
+            output.set_pixel(x * 2, y * 2 + 1, smooth2x_quadrant(e, n(-1, 1), n(0, 1), n(-1, 0)));
+            output.set_pixel(x * 2 + 1, y * 2 + 1, smooth2x_quadrant(e, n(1, 1), n(0, 1), n(1, 0)));
+        }
+    }
+}
+
+// Darkens the last row of each group of `factor` rows, so that every
+// source line is followed by a gap like on a CRT.
+fn scanlines(buffer: &mut RgbBuffer, factor: usize, brightness: f32) {
+    let darken = |value: u8| (value as f32 * brightness) as u8;
+
+    for y in 0..buffer.height() {
+        if y % factor != factor - 1 {
+            continue;
+        }
+        for x in 0..buffer.width() {
+            let (r, g, b) = buffer.get_pixel(x, y);
+            buffer.set_pixel(x, y, (darken(r), darken(g), darken(b)));
+        }
+    }
+}
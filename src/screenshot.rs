@@ -0,0 +1,14 @@
+use std::fs;
+use std::io::{self, Write};
+
+use frame_buffer::RgbBuffer;
+
+// Writes the image as a binary PPM, which needs no external encoder
+pub fn write_ppm(image: &RgbBuffer, path: &str) -> io::Result<()> {
+    let mut file = try!(fs::File::create(path));
+
+    try!(write!(file, "P6\n{} {}\n255\n", image.width(), image.height()));
+    try!(file.write_all(image.data()));
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::thread;
+use std::time::Duration;
+
+use sdl2::Sdl;
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+
+use mixer;
+
+const CHANNELS: u8 = 1;
+const BYTES_PER_SAMPLE: usize = 2;
+const DEVICE_BUFFER_SAMPLES: u16 = 1024;
+
+// Emulation waits whenever more than this much audio is queued
+const TARGET_LATENCY_MS: u32 = 50;
+
+// The most the sample rate is stretched by to correct the queue's level
+const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+
+const WAIT_INTERVAL_MS: u64 = 1;
+
+// Plays the APU's samples through an SDL audio queue. The queue also paces
+// emulation: each frame waits for the device to consume enough audio.
+pub struct SdlAudio {
+    queue: AudioQueue<i16>,
+    sample_rate: u32,
+    target_samples: usize,
+    // how full the queue was, relative to the target, before the last wait
+    fill: f64,
+    buffer: Vec<i16>,
+}
+
+impl SdlAudio {
+    pub fn new(sdl: &Sdl, sample_rate: u32) -> Result<Self, String> {
+        let subsystem = try!(sdl.audio());
+        let spec = AudioSpecDesired {
+            freq: Some(sample_rate as i32),
+            channels: Some(CHANNELS),
+            samples: Some(DEVICE_BUFFER_SAMPLES),
+        };
+        let queue = try!(subsystem.open_queue::<i16>(None, &spec));
+        queue.resume();
+
+        Ok(SdlAudio {
+            queue: queue,
+            sample_rate: sample_rate,
+            target_samples: (sample_rate * TARGET_LATENCY_MS / 1000) as usize,
+            fill: 1.0,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queued_samples(&self) -> usize {
+        self.queue.size() as usize / (BYTES_PER_SAMPLE * CHANNELS as usize)
+    }
+
+    pub fn queue(&mut self, samples: &[f32]) {
+        self.buffer.clear();
+        self.buffer.extend(samples.iter().map(|&s| mixer::to_i16(s)));
+        // these bindings pass the slice's length to SDL as its size in
+        // bytes, so the slice is padded to twice the samples' length for
+        // all of them to be queued. The padding itself isn't.
+        let num_samples = self.buffer.len();
+        self.buffer.resize(num_samples * BYTES_PER_SAMPLE, 0);
+        self.queue.queue(&self.buffer);
+    }
+
+    // Blocks until the queue has drained to the target latency
+    pub fn wait(&mut self) {
+        self.fill = self.queued_samples() as f64 / self.target_samples as f64;
+        while self.queued_samples() > self.target_samples {
+            thread::sleep(Duration::from_millis(WAIT_INTERVAL_MS));
+        }
+    }
+
+    // The factor to scale the sample rate by for the next frame: above 1
+    // when the queue is running low, to avoid underruns, and below 1 when
+    // it's overfull, to avoid drifting behind the picture. The level is
+    // the one seen before waiting, since waiting always drains it to the
+    // target.
+    pub fn rate_adjustment(&self) -> f64 {
+        1.0 + MAX_RATE_ADJUSTMENT * (1.0 - self.fill).max(-1.0).min(1.0)
+    }
+}
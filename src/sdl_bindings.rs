@@ -0,0 +1,203 @@
+use sdl2::keyboard::Keycode;
+use sdl2::controller::Button;
+
+use config::Config;
+use controller;
+use input;
+use sdl_gamepads::PadId;
+
+// The names of the NES's buttons in a bindings file
+const BUTTON_NAMES: [(&'static str, u8); 8] = [
+    ("a", controller::BUTTON_A),
+    ("b", controller::BUTTON_B),
+    ("select", controller::BUTTON_SELECT),
+    ("start", controller::BUTTON_START),
+    ("up", controller::BUTTON_UP),
+    ("down", controller::BUTTON_DOWN),
+    ("left", controller::BUTTON_LEFT),
+    ("right", controller::BUTTON_RIGHT),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Quit,
+    Reset,
+    FastForward,
+    Screenshot,
+    ToggleRecording,
+    ToggleNametables,
+    ToggleSprites,
+    ToggleSpriteHighlights,
+    ToggleNtsc,
+}
+
+const ACTION_NAMES: [(&'static str, Action); 9] = [
+    ("quit", Action::Quit),
+    ("reset", Action::Reset),
+    ("fast_forward", Action::FastForward),
+    ("screenshot", Action::Screenshot),
+    ("record_audio", Action::ToggleRecording),
+    ("nametables", Action::ToggleNametables),
+    ("sprites", Action::ToggleSprites),
+    ("highlight_sprites", Action::ToggleSpriteHighlights),
+    ("ntsc", Action::ToggleNtsc),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    // a player, numbered from 0, and one of their buttons
+    Button(usize, u8),
+    Action(Action),
+}
+
+// Which keys and gamepad buttons do what. Bindings files have a section for
+// each player's keys, [keyboard1] to [keyboard4], one for each player's
+// gamepad, [gamepad1] to [gamepad4], after [gamepad] for all of them, and
+// one for the frontend's actions, each mapping names to SDL key or button
+// names, e.g.
+//
+//     [keyboard1]
+//     a = "Z"
+//     start = "Return"
+//
+//     [gamepad2]
+//     a = "b"
+//
+//     [actions]
+//     fast_forward = "Tab"
+//
+// Pads can be assigned to players in a [gamepads] section, by the GUID SDL
+// gives their model or by their index when connected, e.g.
+//
+//     [gamepads]
+//     player1 = "030000005e0400008e02000014010000"
+//     player2 = 1
+pub struct Bindings {
+    keys: Vec<(Keycode, Binding)>,
+    // for each player
+    pad_buttons: Vec<Vec<(Button, u8)>>,
+    pad_players: Vec<(PadId, usize)>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        // by position, so the NES's B and A are the bottom and right face
+        // buttons
+        let pad_buttons = vec![
+            (Button::A, controller::BUTTON_B),
+            (Button::X, controller::BUTTON_B),
+            (Button::B, controller::BUTTON_A),
+            (Button::Y, controller::BUTTON_A),
+            (Button::Back, controller::BUTTON_SELECT),
+            (Button::Start, controller::BUTTON_START),
+            (Button::DPadUp, controller::BUTTON_UP),
+            (Button::DPadDown, controller::BUTTON_DOWN),
+            (Button::DPadLeft, controller::BUTTON_LEFT),
+            (Button::DPadRight, controller::BUTTON_RIGHT),
+        ];
+
+        Bindings {
+            keys: vec![
+                (Keycode::A, Binding::Button(0, controller::BUTTON_A)),
+                (Keycode::B, Binding::Button(0, controller::BUTTON_B)),
+                (Keycode::RShift, Binding::Button(0, controller::BUTTON_SELECT)),
+                (Keycode::Return, Binding::Button(0, controller::BUTTON_START)),
+                (Keycode::Up, Binding::Button(0, controller::BUTTON_UP)),
+                (Keycode::Down, Binding::Button(0, controller::BUTTON_DOWN)),
+                (Keycode::Left, Binding::Button(0, controller::BUTTON_LEFT)),
+                (Keycode::Right, Binding::Button(0, controller::BUTTON_RIGHT)),
+                (Keycode::O, Binding::Button(1, controller::BUTTON_A)),
+                (Keycode::U, Binding::Button(1, controller::BUTTON_B)),
+                (Keycode::Y, Binding::Button(1, controller::BUTTON_SELECT)),
+                (Keycode::H, Binding::Button(1, controller::BUTTON_START)),
+                (Keycode::I, Binding::Button(1, controller::BUTTON_UP)),
+                (Keycode::K, Binding::Button(1, controller::BUTTON_DOWN)),
+                (Keycode::J, Binding::Button(1, controller::BUTTON_LEFT)),
+                (Keycode::L, Binding::Button(1, controller::BUTTON_RIGHT)),
+                (Keycode::Escape, Binding::Action(Action::Quit)),
+                (Keycode::F2, Binding::Action(Action::ToggleNametables)),
+                (Keycode::F3, Binding::Action(Action::ToggleSprites)),
+                (Keycode::F4, Binding::Action(Action::ToggleSpriteHighlights)),
+                (Keycode::F5, Binding::Action(Action::ToggleNtsc)),
+                (Keycode::F6, Binding::Action(Action::ToggleRecording)),
+                (Keycode::F9, Binding::Action(Action::Reset)),
+                (Keycode::F12, Binding::Action(Action::Screenshot)),
+                (Keycode::Tab, Binding::Action(Action::FastForward)),
+            ],
+            pad_buttons: vec![pad_buttons; input::MAX_PLAYERS],
+            pad_players: Vec::new(),
+        }
+    }
+
+    // Replaces the bindings given in the config, leaving the rest
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), String> {
+        for player in 0..input::MAX_PLAYERS {
+            for &(name, button) in BUTTON_NAMES.iter() {
+                if let Some(key) = try!(config_key(config, &format!("keyboard{}", player + 1), name)) {
+                    self.bind_key(key, Binding::Button(player, button));
+                }
+            }
+        }
+
+        for &(name, action) in ACTION_NAMES.iter() {
+            if let Some(key) = try!(config_key(config, "actions", name)) {
+                self.bind_key(key, Binding::Action(action));
+            }
+        }
+
+        for player in 0..input::MAX_PLAYERS {
+            for section in &["gamepad".to_string(), format!("gamepad{}", player + 1)] {
+                for &(name, button) in BUTTON_NAMES.iter() {
+                    if let Some(pad_name) = config.get_str(section, name) {
+                        let pad_button = try!(Button::from_string(pad_name)
+                            .ok_or(format!("Unknown gamepad button: {}", pad_name)));
+                        let pad_buttons = &mut self.pad_buttons[player];
+                        pad_buttons.retain(|&(b, nes_button)| b != pad_button && nes_button != button);
+                        pad_buttons.push((pad_button, button));
+                    }
+                }
+            }
+        }
+
+        for player in 0..input::MAX_PLAYERS {
+            let key = format!("player{}", player + 1);
+            let id = match (config.get_str("gamepads", &key), config.get_integer("gamepads", &key)) {
+                (Some(guid), _) => PadId::Guid(guid.to_lowercase()),
+                (None, Some(index)) if index >= 0 => PadId::Index(index as u32),
+                (None, Some(index)) => return Err(format!("Invalid gamepad index: {}", index)),
+                (None, None) => continue,
+            };
+            self.pad_players.push((id, player));
+        }
+
+        Ok(())
+    }
+
+    // A key does one thing, so binding it replaces its old binding, as well
+    // as the old key for the same thing
+    fn bind_key(&mut self, key: Keycode, binding: Binding) {
+        self.keys.retain(|&(k, b)| k != key && b != binding);
+        self.keys.push((key, binding));
+    }
+
+    pub fn key(&self, key: Keycode) -> Option<Binding> {
+        self.keys.iter().find(|&&(k, _)| k == key).map(|&(_, binding)| binding)
+    }
+
+    pub fn pad_buttons(&self) -> Vec<Vec<(Button, u8)>> {
+        self.pad_buttons.clone()
+    }
+
+    pub fn pad_players(&self) -> Vec<(PadId, usize)> {
+        self.pad_players.clone()
+    }
+}
+
+fn config_key(config: &Config, section: &str, name: &str) -> Result<Option<Keycode>, String> {
+    match config.get_str(section, name) {
+        Some(key_name) => Keycode::from_name(key_name)
+            .map(Some)
+            .ok_or(format!("Unknown key: {}", key_name)),
+        None => Ok(None),
+    }
+}
@@ -1,77 +1,183 @@
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use sdl2;
 use sdl2::{Sdl, EventPump};
 use sdl2::render::{Texture, Renderer};
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::event::{Event, WindowEventId};
+use sdl2::mouse::Mouse;
+use sdl2::keyboard::{Keycode, LCTRLMOD, RCTRLMOD};
 
 
-use frontend::Frontend;
+use frontend;
+use frontend::{Frontend, FrontendBuilder};
 use cartridge;
 use cartridge::Cartridge;
-use nrom_cartridge::NromCartridge;
 use nes::NesWithCartridge;
-use image::NesImage;
-use renderer;
+use image::{NesImage, TvSystem};
 use debug::NesDebug;
+use frame_buffer::{FrameBuffer, RgbBuffer};
+use nametable_viewer::NametableViewer;
+use rgb_palette::RgbPalette;
+use video::{VideoOptions, VideoPipeline};
+use screenshot;
+use sdl_audio::SdlAudio;
+use wav::{WavWriter, ChannelRecording};
+use mixer;
+use mixer::{AudioOptions, Channel};
+use sprite_inspector;
+use sprite_inspector::SpriteInspector;
 use ppu;
-use io;
+use input;
+use input::InputOptions;
+use sdl_gamepads::SdlGamepads;
+use sdl_bindings::{Bindings, Binding, Action};
 
-const SCALE: u32 = 2;
-const WINDOW_WIDTH: u32 = ppu::DISPLAY_WIDTH as u32 * SCALE;
-const WINDOW_HEIGHT: u32 = ppu::DISPLAY_HEIGHT as u32 * SCALE;
+// Used to pace frames when no audio device is available (60.0988Hz)
+const FALLBACK_FRAME_MICROS: u64 = 16639;
 
 enum MetaControl {
     Quit,
 }
 
+#[derive(PartialEq, Eq)]
+enum View {
+    Game,
+    Nametables,
+    Sprites,
+}
+
 pub struct SdlFrontend<'a, C: Cartridge> {
     nes: NesWithCartridge<C>,
     sdl: Sdl,
     events: EventPump,
     renderer: Renderer<'a>,
     texture: Texture,
-}
-
-struct SdlFrame<'a> {
-    buffer: &'a mut [u8],
-    pitch: usize,
-}
-
-impl<'a> SdlFrame<'a> {
-    fn new(buffer: &'a mut [u8], pitch: usize) -> Self {
-        SdlFrame {
-            buffer: buffer,
-            pitch: pitch,
-        }
-    }
+    view: View,
+    nametable_viewer: NametableViewer,
+    nametable_texture: Texture,
+    sprite_inspector: SpriteInspector,
+    sprite_texture: Texture,
+    highlight_sprites: bool,
+    frame: FrameBuffer,
+    video: VideoPipeline,
+    output_size: (usize, usize),
+    window_width: u32,
+    window_height: u32,
+    num_screenshots: usize,
+    audio: Option<SdlAudio>,
+    frame_start: Instant,
+    recording: Option<WavWriter>,
+    num_recordings: usize,
+    channel_recording: Option<ChannelRecording>,
+    keyboard_buttons: [u8; input::MAX_PLAYERS],
+    gamepads: Option<SdlGamepads>,
+    bindings: Bindings,
+    fast_forward: bool,
+    // where the mouse points in the NES's picture, for light guns
+    pointer: Option<(usize, usize)>,
+    mouse_button: bool,
+    // horizontal mouse movement during the frame, for paddles
+    mouse_dx: i32,
 }
 
 impl<'a, C: Cartridge> SdlFrontend<'a, C> {
-    pub fn new(cartridge: C) -> Self {
+    pub fn new(cartridge: C, tv_system: TvSystem, options: VideoOptions,
+               audio_options: AudioOptions,
+               input_options: InputOptions,
+               bindings: Bindings) -> Self {
+        let scale = options.scale;
+        let video = VideoPipeline::new(options);
+        let (display_width, display_height) = video.display_size(ppu::DISPLAY_WIDTH, ppu::DISPLAY_HEIGHT);
+        let window_width = (display_width * scale) as u32;
+        let window_height = (display_height * scale) as u32;
+
         let sdl = sdl2::init().expect("SDL2 initialization failed");
         let window = sdl.video().unwrap()
-            .window("NES", WINDOW_WIDTH, WINDOW_HEIGHT)
+            .window("NES", window_width, window_height)
             .build()
             .expect("Failed to create window");
         let events = sdl.event_pump().expect("Failed to initialise events");
+
+        let audio = match SdlAudio::new(&sdl, mixer::DEFAULT_SAMPLE_RATE) {
+            Ok(audio) => Some(audio),
+            Err(e) => {
+                println!("Audio unavailable: {}", e);
+                None
+            }
+        };
+        let gamepads = match SdlGamepads::new(&sdl, bindings.pad_buttons(), bindings.pad_players()) {
+            Ok(gamepads) => Some(gamepads),
+            Err(e) => {
+                println!("Gamepads unavailable: {}", e);
+                None
+            }
+        };
         let renderer = window.renderer().build()
             .expect("Failed to initialise renderer");
 
         let texture = renderer.create_texture_streaming(
-            PixelFormatEnum::RGB24, 256, 240)
+            PixelFormatEnum::RGB24, ppu::DISPLAY_WIDTH as u32, ppu::DISPLAY_HEIGHT as u32)
+            .expect("Failed to initialise texture");
+
+        let nametable_texture = renderer.create_texture_streaming(
+            PixelFormatEnum::RGB24, ppu::NAMETABLE_VIEW_WIDTH as u32, ppu::NAMETABLE_VIEW_HEIGHT as u32)
+            .expect("Failed to initialise texture");
+
+        let sprite_texture = renderer.create_texture_streaming(
+            PixelFormatEnum::RGB24, sprite_inspector::SHEET_WIDTH as u32, sprite_inspector::SHEET_HEIGHT as u32)
             .expect("Failed to initialise texture");
 
+        let mut nes = NesWithCartridge::new(cartridge, tv_system);
+        if let Some(ref audio) = audio {
+            nes.io.apu.mixer().set_sample_rate(audio.sample_rate());
+        }
+
+        audio_options.apply(nes.io.apu.mixer());
+        // recordings can be started at any time
+        nes.io.apu.mixer().enable_recording_track();
+        input_options.apply(&mut nes.io);
+        let sample_rate = nes.io.apu.mixer().sample_rate();
+        let recording = audio_options.record_path.map(|path| {
+            WavWriter::create(&path, sample_rate).expect("Failed to create audio recording")
+        });
+        let channel_recording = audio_options.channel_record_prefix.map(|prefix| {
+            ChannelRecording::create(&prefix, sample_rate).expect("Failed to create channel recordings")
+        });
+
         SdlFrontend {
-            nes: NesWithCartridge::new(cartridge),
+            nes: nes,
             sdl: sdl,
             events: events,
             renderer: renderer,
             texture: texture,
+            view: View::Game,
+            nametable_viewer: NametableViewer::new(),
+            nametable_texture: nametable_texture,
+            sprite_inspector: SpriteInspector::new(),
+            sprite_texture: sprite_texture,
+            highlight_sprites: false,
+            frame: FrameBuffer::new(ppu::DISPLAY_WIDTH, ppu::DISPLAY_HEIGHT),
+            video: video,
+            output_size: (ppu::DISPLAY_WIDTH, ppu::DISPLAY_HEIGHT),
+            window_width: window_width,
+            window_height: window_height,
+            num_screenshots: 0,
+            audio: audio,
+            frame_start: Instant::now(),
+            recording: recording,
+            num_recordings: 0,
+            channel_recording: channel_recording,
+            keyboard_buttons: [0; input::MAX_PLAYERS],
+            gamepads: gamepads,
+            bindings: bindings,
+            fast_forward: false,
+            pointer: None,
+            mouse_button: false,
+            mouse_dx: 0,
         }
     }
 
@@ -79,30 +185,253 @@ impl<'a, C: Cartridge> SdlFrontend<'a, C> {
         self.nes.init().expect("Failed to initialise nes");
     }
 
+    fn update_texture(&mut self) {
+        let output = self.video.process(&self.frame);
+
+        let size = (output.width(), output.height());
+        if size != self.output_size {
+            self.texture = self.renderer.create_texture_streaming(
+                PixelFormatEnum::RGB24, size.0 as u32, size.1 as u32)
+                .expect("Failed to initialise texture");
+            self.output_size = size;
+        }
+
+        copy_rgb_buffer(output, &mut self.texture);
+    }
+
+    fn save_screenshot(&mut self) {
+        let path = format!("screenshot-{}.ppm", self.num_screenshots);
+        match screenshot::write_ppm(self.video.output(), &path) {
+            Ok(()) => {
+                println!("Saved {}", path);
+                self.num_screenshots += 1;
+            }
+            Err(e) => println!("Failed to save {}: {}", path, e),
+        }
+    }
+
+    // Starts recording to a new file, or stops the current recording
+    fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(wav) => {
+                match wav.finish() {
+                    Ok(()) => println!("Stopped recording audio"),
+                    Err(e) => println!("Failed to save recording: {}", e),
+                }
+            }
+            None => {
+                let path = format!("recording-{}.wav", self.num_recordings);
+                let sample_rate = self.nes.io.apu.mixer().sample_rate();
+                match WavWriter::create(&path, sample_rate) {
+                    Ok(wav) => {
+                        println!("Recording audio to {}", path);
+                        self.recording = Some(wav);
+                        self.num_recordings += 1;
+                    }
+                    Err(e) => println!("Failed to create {}: {}", path, e),
+                }
+            }
+        }
+    }
+
+    fn record_audio(&mut self) {
+        let failed = match self.recording {
+            Some(ref mut wav) => wav.write_samples(self.nes.io.apu.mixer().recorded_samples()).is_err(),
+            None => false,
+        };
+        if failed {
+            println!("Failed to record audio");
+            self.toggle_recording();
+        }
+
+        let failed = match self.channel_recording {
+            Some(ref mut channels) => channels.write_frame(self.nes.io.apu.mixer()).is_err(),
+            None => false,
+        };
+        if failed {
+            println!("Failed to record channels");
+            if let Some(channels) = self.channel_recording.take() {
+                if let Err(e) = channels.finish() {
+                    println!("Failed to save channel recordings: {}", e);
+                }
+            }
+        }
+    }
+
+    // Mutes the channel, or with ctrl held solos it
+    fn toggle_channel(&mut self, channel: Channel, solo: bool) {
+        let mixer = self.nes.io.apu.mixer();
+        if solo {
+            let solo = if mixer.solo() == Some(channel) { None } else { Some(channel) };
+            mixer.set_solo(solo);
+        } else {
+            mixer.toggle_muted(channel);
+        }
+
+        let status: Vec<String> = mixer::CHANNELS.iter().map(|&c| {
+            format!("{}: {}", c.name(), if mixer.is_audible(c) { "on" } else { "off" })
+        }).collect();
+        println!("{}", status.join(", "));
+    }
+
+    // Maps a rectangle in an image of the given size onto the window
+    fn window_rect(&self, x: isize, y: isize, width: usize, height: usize,
+                   image_width: usize, image_height: usize) -> Rect {
+        let window_width = self.window_width as usize;
+        let window_height = self.window_height as usize;
+
+        Rect::new((x * window_width as isize / image_width as isize) as i32,
+                  (y * window_height as isize / image_height as isize) as i32,
+                  (width * window_width / image_width) as u32,
+                  (height * window_height / image_height) as u32)
+    }
+
+    // The pixel of the NES's picture at a point in the window, if the
+    // picture is being shown there
+    fn picture_position(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        if self.view != View::Game || x < 0 || y < 0 {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let (window_width, window_height) = (self.window_width as usize, self.window_height as usize);
+        if x >= window_width || y >= window_height {
+            return None;
+        }
+
+        let overscan = self.video.overscan();
+        let visible_width = ppu::DISPLAY_WIDTH.saturating_sub(overscan.left + overscan.right);
+        let visible_height = ppu::DISPLAY_HEIGHT.saturating_sub(overscan.top + overscan.bottom);
+        Some((overscan.left + x * visible_width / window_width,
+              overscan.top + y * visible_height / window_height))
+    }
+
     fn render_texture(&mut self) {
+        self.update_texture();
+
+        self.renderer.clear();
+        self.renderer.copy(&self.texture, None, Some(Rect::new(0, 0, self.window_width, self.window_height)));
+        if self.highlight_sprites {
+            self.render_sprite_highlights();
+        }
+        self.renderer.present();
+    }
+
+    fn render_sprite_highlights(&mut self) {
+        self.sprite_inspector.update(&mut self.nes).expect("Failed to inspect sprites");
+
+        // sprite positions are relative to the full display, not the cropped picture
+        let overscan = self.video.overscan();
+        let visible_width = ppu::DISPLAY_WIDTH.saturating_sub(overscan.left + overscan.right);
+        let visible_height = ppu::DISPLAY_HEIGHT.saturating_sub(overscan.top + overscan.bottom);
+
+        for entry in self.sprite_inspector.entries() {
+            if entry.off_screen {
+                continue;
+            }
+
+            let rect = self.window_rect(entry.sprite.x as isize - overscan.left as isize,
+                                        entry.sprite.y as isize - overscan.top as isize,
+                                        ppu::TILE_WIDTH as usize, entry.height,
+                                        visible_width, visible_height);
+            self.renderer.set_draw_color(sprite_highlight_colour(entry));
+            self.renderer.draw_rect(rect).expect("Failed to draw sprite highlight");
+        }
+        self.renderer.set_draw_color(Color::RGB(0, 0, 0));
+    }
+
+    fn render_sprites(&mut self) {
+        self.sprite_inspector.update(&mut self.nes).expect("Failed to inspect sprites");
+        copy_frame_buffer(self.sprite_inspector.sheet(), self.video.palette(), &mut self.sprite_texture);
+
+        // scale the sheet to fit the window, keeping it centred
+        let scale = (self.window_width as usize / sprite_inspector::SHEET_WIDTH)
+            .min(self.window_height as usize / sprite_inspector::SHEET_HEIGHT)
+            .max(1);
+        let offset_x = (self.window_width as usize).saturating_sub(sprite_inspector::SHEET_WIDTH * scale) / 2;
+        let offset_y = (self.window_height as usize).saturating_sub(sprite_inspector::SHEET_HEIGHT * scale) / 2;
+
         self.renderer.clear();
-        self.renderer.copy(&self.texture, None, Some(Rect::new(0, 0, 512, 480)));
+        self.renderer.copy(&self.sprite_texture, None,
+                           Some(Rect::new(offset_x as i32, offset_y as i32,
+                                          (sprite_inspector::SHEET_WIDTH * scale) as u32,
+                                          (sprite_inspector::SHEET_HEIGHT * scale) as u32)));
+
+        for entry in self.sprite_inspector.entries() {
+            let (x, y) = entry.sheet_position();
+            self.renderer.set_draw_color(sprite_highlight_colour(entry));
+            let rect = Rect::new((offset_x + x * scale) as i32, (offset_y + y * scale) as i32,
+                                 (sprite_inspector::SHEET_CELL_WIDTH * scale) as u32,
+                                 (sprite_inspector::SHEET_CELL_HEIGHT * scale) as u32);
+            self.renderer.draw_rect(rect).expect("Failed to draw sprite cell");
+        }
+        self.renderer.set_draw_color(Color::RGB(0, 0, 0));
+
+        self.renderer.present();
+    }
+
+    fn render_nametables(&mut self) {
+        self.nametable_viewer.update(&mut self.nes).expect("Failed to render nametables");
+        copy_frame_buffer(self.nametable_viewer.frame(), self.video.palette(), &mut self.nametable_texture);
+
+        self.renderer.clear();
+        self.renderer.copy(&self.nametable_texture, None,
+                           Some(Rect::new(0, 0, self.window_width, self.window_height)));
+
+        self.renderer.set_draw_color(Color::RGB(255, 0, 0));
+        for rect in self.nametable_viewer.viewport_rects() {
+            let rect = self.window_rect(rect.x as isize, rect.y as isize, rect.width, rect.height,
+                                        ppu::NAMETABLE_VIEW_WIDTH, ppu::NAMETABLE_VIEW_HEIGHT);
+            self.renderer.draw_rect(rect).expect("Failed to draw viewport");
+        }
+        self.renderer.set_draw_color(Color::RGB(0, 0, 0));
+
         self.renderer.present();
     }
 
     fn emulate_frame(&mut self) {
-        let nes = &mut self.nes;
-        self.texture.with_lock(None, |buffer, pitch| {
-            let mut frame = SdlFrame::new(buffer, pitch);
-            nes.emulate_frame(&mut frame).expect("Emulation failed");
-        }).unwrap();
+        self.nes.emulate_frame(&mut self.frame).expect("Emulation failed");
     }
 
     fn frame(&mut self) -> Option<MetaControl> {
         let meta = self.get_input();
 
         self.emulate_frame();
-        self.render_texture();
-        thread::sleep(Duration::from_millis(10));
+        match self.view {
+            View::Game => self.render_texture(),
+            View::Nametables => self.render_nametables(),
+            View::Sprites => self.render_sprites(),
+        }
+        self.record_audio();
+        self.play_audio();
 
         meta
     }
 
+    // Queues the frame's audio, and waits for the device to catch up
+    fn play_audio(&mut self) {
+        // run flat out, dropping the audio rather than letting it queue up
+        if self.fast_forward {
+            self.frame_start = Instant::now();
+            return;
+        }
+
+        match self.audio {
+            Some(ref mut audio) => {
+                audio.queue(self.nes.io.apu.samples());
+                audio.wait();
+                self.nes.io.apu.mixer().adjust_rate(audio.rate_adjustment());
+            }
+            None => {
+                let frame_duration = Duration::from_micros(FALLBACK_FRAME_MICROS);
+                let elapsed = self.frame_start.elapsed();
+                if elapsed < frame_duration {
+                    thread::sleep(frame_duration - elapsed);
+                }
+            }
+        }
+        self.frame_start = Instant::now();
+    }
+
     fn print_state(&mut self) {
         println!("\nRAM{}", self.nes.dump_memory(0..0x7ff));
         println!("\nVRAM{}", self.nes.ppu_dump_memory(0x2000..0x2fff));
@@ -112,41 +441,100 @@ impl<'a, C: Cartridge> SdlFrontend<'a, C> {
     }
 
     fn get_input(&mut self) -> Option<MetaControl> {
-        for event in self.events.poll_iter() {
+        let events: Vec<Event> = self.events.poll_iter().collect();
+        for event in events {
             match event {
-                Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    return Some(MetaControl::Quit);
-                }
-                Event::KeyDown { keycode: Some(Keycode::Return), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_START);
-                }
-                Event::KeyDown { keycode: Some(Keycode::RShift), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_SELECT);
-                }
-                Event::KeyDown { keycode: Some(Keycode::A), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_A);
+                Event::Quit {..} => return Some(MetaControl::Quit),
+                Event::KeyDown { keycode: Some(keycode), keymod, repeat, .. } => {
+                    match self.bindings.key(keycode) {
+                        Some(Binding::Button(player, button)) => self.keyboard_buttons[player] |= button,
+                        Some(Binding::Action(action)) => {
+                            if !repeat {
+                                if let Some(meta) = self.perform(action) {
+                                    return Some(meta);
+                                }
+                            }
+                        }
+                        None => self.channel_key(keycode, keymod.intersects(LCTRLMOD | RCTRLMOD)),
+                    }
                 }
-                Event::KeyDown { keycode: Some(Keycode::B), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_B);
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    match self.bindings.key(keycode) {
+                        Some(Binding::Button(player, button)) => self.keyboard_buttons[player] &= !button,
+                        Some(Binding::Action(Action::FastForward)) => self.fast_forward = false,
+                        _ => {}
+                    }
                 }
-                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_LEFT);
+                Event::MouseMotion { x, y, xrel, .. } => {
+                    self.pointer = self.picture_position(x, y);
+                    self.mouse_dx += xrel;
                 }
-                Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_RIGHT);
+                Event::MouseButtonDown { mouse_btn: Mouse::Left, .. } => self.mouse_button = true,
+                Event::MouseButtonUp { mouse_btn: Mouse::Left, .. } => self.mouse_button = false,
+                Event::Window { win_event_id: WindowEventId::Leave, .. } => self.pointer = None,
+                event => {
+                    if let Some(ref mut gamepads) = self.gamepads {
+                        gamepads.handle_event(&event);
+                    }
                 }
-                Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_UP);
-                }
-                Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
-                    self.nes.io.joy1_press(io::BUTTON_DOWN);
-                }
-                _ => {}
             }
         }
 
+        // each player's buttons are whatever is held on the keyboard or pad
+        for player in 0..input::MAX_PLAYERS {
+            let pad_buttons = self.gamepads.as_ref().map_or(0, |gamepads| gamepads.buttons(player));
+            self.nes.io.set_buttons(player, self.keyboard_buttons[player] | pad_buttons);
+        }
+        self.nes.io.set_pointer(self.pointer, self.mouse_dx, self.mouse_button);
+        self.mouse_dx = 0;
+
         None
     }
+
+    fn perform(&mut self, action: Action) -> Option<MetaControl> {
+        match action {
+            Action::Quit => return Some(MetaControl::Quit),
+            Action::Reset => self.nes.reset().expect("Failed to reset"),
+            // until the key is released
+            Action::FastForward => self.fast_forward = true,
+            Action::Screenshot => self.save_screenshot(),
+            Action::ToggleRecording => self.toggle_recording(),
+            Action::ToggleNametables => {
+                self.view = if self.view == View::Nametables {
+                    View::Game
+                } else {
+                    View::Nametables
+                };
+            }
+            Action::ToggleSprites => {
+                self.view = if self.view == View::Sprites {
+                    View::Game
+                } else {
+                    self.sprite_inspector.update(&mut self.nes).expect("Failed to inspect sprites");
+                    println!("\nSprites\n{}", self.sprite_inspector);
+                    View::Sprites
+                };
+            }
+            Action::ToggleSpriteHighlights => self.highlight_sprites = !self.highlight_sprites,
+            Action::ToggleNtsc => self.video.toggle_ntsc(),
+        }
+        None
+    }
+
+    // The number keys mute the audio channels in order, or solo them with
+    // ctrl held
+    fn channel_key(&mut self, keycode: Keycode, solo: bool) {
+        let index = match keycode {
+            Keycode::Num1 => 0,
+            Keycode::Num2 => 1,
+            Keycode::Num3 => 2,
+            Keycode::Num4 => 3,
+            Keycode::Num5 => 4,
+            Keycode::Num6 => 5,
+            _ => return,
+        };
+        self.toggle_channel(mixer::CHANNELS[index], solo);
+    }
 }
 
 impl<'a, C: Cartridge> Frontend for SdlFrontend<'a, C> {
@@ -164,107 +552,78 @@ impl<'a, C: Cartridge> Frontend for SdlFrontend<'a, C> {
             }
         }
 
+        if self.recording.is_some() {
+            self.toggle_recording();
+        }
+        if let Some(channels) = self.channel_recording.take() {
+            if let Err(e) = channels.finish() {
+                println!("Failed to save channel recordings: {}", e);
+            }
+        }
+
         self.print_state();
     }
 }
 
 
-pub fn init(image: &NesImage) -> cartridge::Result<Box<Frontend>> {
-    match image.header.mapper_number {
-        cartridge::NROM => {
-            match try!(NromCartridge::new(image)) {
-                NromCartridge::HorizontalMirroring(cartridge) => {
-                    Ok(Box::new(SdlFrontend::new(cartridge)))
-                }
-                NromCartridge::VerticalMirroring(cartridge) => {
-                    Ok(Box::new(SdlFrontend::new(cartridge)))
-                }
-            }
-        }
-        other => Err(cartridge::Error::UnknownMapper(other)),
+struct SdlFrontendBuilder {
+    tv_system: TvSystem,
+    options: VideoOptions,
+    audio: AudioOptions,
+    input: InputOptions,
+    bindings: Bindings,
+}
+
+impl FrontendBuilder for SdlFrontendBuilder {
+    fn build<C: Cartridge + 'static>(self, cartridge: C) -> Box<Frontend> {
+        Box::new(SdlFrontend::new(cartridge, self.tv_system, self.options, self.audio, self.input, self.bindings))
     }
 }
 
-impl<'a> SdlFrame<'a> {
-    fn convert_colour(nes_colour: u8) -> (u8, u8, u8) {
-        match nes_colour {
-            0x00 => (124, 124, 124),
-            0x01 => (0, 0, 252),
-            0x02 => (0, 0, 118),
-            0x03 => (68, 40, 188),
-            0x04 => (140, 0, 32),
-            0x05 => (168, 16, 0),
-            0x06 => (168, 0, 16),
-            0x07 => (136, 20, 0),
-            0x08 => (80, 48, 0),
-            0x09 => (0, 120, 0),
-            0x0a => (0, 104, 0),
-            0x0b => (0, 88, 0),
-            0x0c => (0, 64, 88),
-            0x0d => (0, 0, 0),
-            0x0e => (0, 0, 0),
-            0x0f => (0, 0, 0),
-            0x10 => (188, 188, 188),
-            0x11 => (0, 120, 248),
-            0x12 => (0, 88, 248),
-            0x13 => (104, 68, 252),
-            0x14 => (216, 0, 204),
-            0x15 => (228, 0, 88),
-            0x16 => (248, 56, 0),
-            0x17 => (228, 92, 16),
-            0x18 => (172, 124, 0),
-            0x19 => (0, 184, 0),
-            0x1a => (0, 168, 0),
-            0x1b => (0, 168, 68),
-            0x1c => (0, 136, 136),
-            0x1d => (0, 0, 0),
-            0x1e => (0, 0, 0),
-            0x1f => (0, 0, 0),
-            0x20 => (248, 248, 248),
-            0x21 => (60, 188, 252),
-            0x22 => (104, 136, 252),
-            0x23 => (152, 120, 248),
-            0x24 => (248, 120, 248),
-            0x25 => (248, 88, 152),
-            0x26 => (248, 120, 88),
-            0x27 => (252, 160, 68),
-            0x28 => (248, 184, 0),
-            0x29 => (184, 248, 24),
-            0x2a => (88, 216, 84),
-            0x2b => (88, 248, 152),
-            0x2c => (0, 232, 216),
-            0x2d => (120, 120, 120),
-            0x2e => (0, 0, 0),
-            0x2f => (0, 0, 0),
-            0x30 => (252, 252, 252),
-            0x31 => (164, 228, 252),
-            0x32 => (184, 184, 248),
-            0x33 => (216, 184, 248),
-            0x34 => (248, 184, 248),
-            0x35 => (248, 164, 192),
-            0x36 => (240, 208, 176),
-            0x37 => (252, 224, 168),
-            0x38 => (248, 216, 120),
-            0x39 => (216, 248, 120),
-            0x3a => (184, 248, 184),
-            0x3b => (184, 248, 216),
-            0x3c => (0, 252, 252),
-            0x3d => (216, 216, 216),
-            0x3e => (0, 0, 0),
-            0x3f => (0, 0, 0),
-            _ => panic!("{:2x}", nes_colour),
-        }
+pub fn init(image: &NesImage,
+            options: VideoOptions,
+            audio: AudioOptions,
+            input: InputOptions,
+            bindings: Bindings) -> cartridge::Result<Box<Frontend>> {
+    frontend::init(image, SdlFrontendBuilder {
+        tv_system: image.header.tv_system,
+        options: options,
+        audio: audio,
+        input: input,
+        bindings: bindings,
+    })
+}
+
+fn sprite_highlight_colour(entry: &sprite_inspector::SpriteEntry) -> Color {
+    if entry.off_screen {
+        Color::RGB(128, 128, 128)
+    } else if entry.dropped() {
+        Color::RGB(255, 255, 0)
+    } else {
+        Color::RGB(0, 255, 0)
     }
 }
 
-impl<'a> renderer::Frame for SdlFrame<'a> {
-    fn set_pixel(&mut self, x: usize, y: usize, colour: u8) {
-        let offset = y * self.pitch + x * 3;
-        if offset + 2 < self.buffer.len() {
-            let (r, g, b) = Self::convert_colour(colour);
-            self.buffer[offset + 0] = r;
-            self.buffer[offset + 1] = g;
-            self.buffer[offset + 2] = b;
+fn copy_rgb_buffer(rgb_buffer: &RgbBuffer, texture: &mut Texture) {
+    texture.with_lock(None, |buffer, pitch| {
+        for y in 0..rgb_buffer.height() {
+            let row = rgb_buffer.row(y);
+            let offset = y * pitch;
+            buffer[offset..(offset + row.len())].copy_from_slice(row);
         }
-    }
+    }).unwrap();
+}
+
+fn copy_frame_buffer(frame_buffer: &FrameBuffer, palette: &RgbPalette, texture: &mut Texture) {
+    texture.with_lock(None, |buffer, pitch| {
+        for y in 0..frame_buffer.height() {
+            for x in 0..frame_buffer.width() {
+                let offset = y * pitch + x * 3;
+                let (r, g, b) = palette.rgb(frame_buffer.get_pixel(x, y));
+                buffer[offset + 0] = r;
+                buffer[offset + 1] = g;
+                buffer[offset + 2] = b;
+            }
+        }
+    }).unwrap();
 }
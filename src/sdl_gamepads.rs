@@ -0,0 +1,147 @@
+use sdl2::Sdl;
+use sdl2::GameControllerSubsystem;
+use sdl2::controller::{GameController, Axis, Button};
+use sdl2::event::Event;
+
+use controller;
+use input;
+
+// How far an analog stick must be pushed to count as a d-pad press
+const STICK_THRESHOLD: i16 = 16384;
+
+struct Pad {
+    controller: GameController,
+    player: usize,
+    buttons: u8,
+    stick: u8,
+}
+
+// Identifies a pad for assigning it to a player: by the GUID of its model,
+// or by SDL's index for it when it's connected
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PadId {
+    Guid(String),
+    Index(u32),
+}
+
+// Gamepads plugged in through SDL's GameController API. Each pad is given
+// to the player it's assigned to when it's connected, including those
+// connected at startup, or else to the first player without one.
+pub struct SdlGamepads {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Pad>,
+    // which NES button each of the pads' buttons is, for each player
+    bindings: Vec<Vec<(Button, u8)>>,
+    assignments: Vec<(PadId, usize)>,
+}
+
+impl SdlGamepads {
+    pub fn new(sdl: &Sdl, bindings: Vec<Vec<(Button, u8)>>,
+               assignments: Vec<(PadId, usize)>) -> Result<Self, String> {
+        Ok(SdlGamepads {
+            subsystem: try!(sdl.game_controller()),
+            pads: Vec::new(),
+            bindings: bindings,
+            assignments: assignments,
+        })
+    }
+
+    // The buttons held on the given player's pad
+    pub fn buttons(&self, player: usize) -> u8 {
+        self.pads.iter()
+            .filter(|pad| pad.player == player)
+            .fold(0, |buttons, pad| buttons | pad.buttons | pad.stick)
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => self.connect(which as u32),
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(index) = self.pads.iter().position(|pad| pad.controller.instance_id() == which) {
+                    let pad = self.pads.remove(index);
+                    println!("Gamepad for player {} disconnected", pad.player + 1);
+                }
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                if let Some((pad, button)) = self.nes_button(which, button) {
+                    pad.buttons |= button;
+                }
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                if let Some((pad, button)) = self.nes_button(which, button) {
+                    pad.buttons &= !button;
+                }
+            }
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                if let Some(pad) = self.pad(which) {
+                    let (negative, positive) = match axis {
+                        Axis::LeftX => (controller::BUTTON_LEFT, controller::BUTTON_RIGHT),
+                        Axis::LeftY => (controller::BUTTON_UP, controller::BUTTON_DOWN),
+                        _ => return,
+                    };
+                    pad.stick &= !(negative | positive);
+                    if value <= -STICK_THRESHOLD {
+                        pad.stick |= negative;
+                    } else if value >= STICK_THRESHOLD {
+                        pad.stick |= positive;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn connect(&mut self, device_index: u32) {
+        let controller = match self.subsystem.open(device_index) {
+            Ok(controller) => controller,
+            Err(e) => {
+                println!("Failed to open gamepad: {:?}", e);
+                return;
+            }
+        };
+
+        // the mapping starts with the GUID
+        let guid = controller.mapping().split(',').next().unwrap_or("").to_string();
+        let assigned = self.assignments.iter()
+            .filter(|&&(ref id, _)| *id == PadId::Guid(guid.clone()) || *id == PadId::Index(device_index))
+            .map(|&(_, player)| player)
+            .find(|&player| self.is_free(player));
+
+        let player = match assigned.or((0..input::MAX_PLAYERS).find(|&p| self.is_free(p))) {
+            Some(player) => player,
+            None => {
+                println!("No free player for gamepad {}", controller.name());
+                return;
+            }
+        };
+
+        println!("Gamepad {} ({}) connected for player {}", controller.name(), guid, player + 1);
+        self.pads.push(Pad {
+            controller: controller,
+            player: player,
+            buttons: 0,
+            stick: 0,
+        });
+    }
+
+    fn is_free(&self, player: usize) -> bool {
+        self.pads.iter().all(|pad| pad.player != player)
+    }
+
+    // The pad with the given instance ID, and the NES button its player has
+    // bound to one of its buttons
+    fn nes_button(&mut self, instance_id: i32, button: Button) -> Option<(&mut Pad, u8)> {
+        let bindings = &self.bindings;
+        self.pads.iter_mut()
+            .find(|pad| pad.controller.instance_id() == instance_id)
+            .and_then(|pad| {
+                bindings[pad.player].iter()
+                    .find(|&&(b, _)| b == button)
+                    .map(|&(_, nes_button)| (pad, nes_button))
+            })
+    }
+
+    fn pad(&mut self, instance_id: i32) -> Option<&mut Pad> {
+        self.pads.iter_mut().find(|pad| pad.controller.instance_id() == instance_id)
+    }
+}
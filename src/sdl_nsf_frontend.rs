@@ -0,0 +1,151 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sdl2;
+use sdl2::EventPump;
+use sdl2::render::Renderer;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+
+use frontend::Frontend;
+use nsf_player::NsfPlayer;
+use sdl_audio::SdlAudio;
+use wav::WavWriter;
+
+const WINDOW_WIDTH: u32 = 256;
+const WINDOW_HEIGHT: u32 = 64;
+
+// Plays an NSF through the audio device. The window is only there to
+// receive key presses: left and right change track.
+pub struct SdlNsfFrontend<'a> {
+    player: NsfPlayer,
+    events: EventPump,
+    renderer: Renderer<'a>,
+    audio: Option<SdlAudio>,
+    recording: Option<WavWriter>,
+    frame_duration: Duration,
+    frame_start: Instant,
+}
+
+impl<'a> SdlNsfFrontend<'a> {
+    pub fn new(mut player: NsfPlayer) -> Self {
+        let sdl = sdl2::init().expect("Failed to initialise SDL");
+        let video = sdl.video().expect("Failed to initialise video");
+
+        let window = video.window(&player.header().name, WINDOW_WIDTH, WINDOW_HEIGHT)
+            .build()
+            .expect("Failed to create window");
+
+        let renderer = window.renderer().build().expect("Failed to initialise renderer");
+        let events = sdl.event_pump().expect("Failed to initialise events");
+
+        let audio = match SdlAudio::new(&sdl, player.mixer().sample_rate()) {
+            Ok(audio) => {
+                player.set_sample_rate(audio.sample_rate());
+                Some(audio)
+            }
+            Err(e) => {
+                println!("Audio unavailable: {}", e);
+                None
+            }
+        };
+
+        let sample_rate = player.mixer().sample_rate();
+        let recording = player.audio_options().record_path.as_ref().map(|path| {
+            WavWriter::create(path, sample_rate).expect("Failed to create audio recording")
+        });
+
+        let frame_duration = Duration::from_micros(player.header().speed() as u64);
+
+        SdlNsfFrontend {
+            player: player,
+            events: events,
+            renderer: renderer,
+            audio: audio,
+            recording: recording,
+            frame_duration: frame_duration,
+            frame_start: Instant::now(),
+        }
+    }
+
+    fn change_track(&mut self, track: u8) {
+        if track >= 1 && track <= self.player.header().num_songs {
+            self.player.start_track(track).expect("Failed to start track");
+        }
+    }
+
+    // Returns false when the player should quit
+    fn get_input(&mut self) -> bool {
+        let events: Vec<Event> = self.events.poll_iter().collect();
+        for event in events {
+            match event {
+                Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    return false;
+                }
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
+                    let track = self.player.track();
+                    self.change_track(track.saturating_sub(1));
+                }
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
+                    let track = self.player.track();
+                    self.change_track(track.saturating_add(1));
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+    // Queues the frame's audio, and waits for the device to catch up
+    fn play_audio(&mut self) {
+        match self.audio {
+            Some(ref mut audio) => {
+                audio.queue(self.player.samples());
+                audio.wait();
+                self.player.mixer().adjust_rate(audio.rate_adjustment());
+            }
+            None => {
+                let elapsed = self.frame_start.elapsed();
+                if elapsed < self.frame_duration {
+                    thread::sleep(self.frame_duration - elapsed);
+                }
+            }
+        }
+        self.frame_start = Instant::now();
+    }
+}
+
+impl<'a> Frontend for SdlNsfFrontend<'a> {
+    fn print_rom_dump(&mut self) {
+        self.player.print_info();
+    }
+
+    fn run(&mut self) {
+        let track = self.player.track();
+        self.player.start_track(track).expect("Failed to start track");
+
+        self.renderer.clear();
+        self.renderer.present();
+
+        while self.get_input() {
+            self.player.play_frame().expect("Emulation failed");
+
+            let failed = match self.recording {
+                Some(ref mut wav) => wav.write_samples(self.player.recorded_samples()).is_err(),
+                None => false,
+            };
+            if failed {
+                println!("Failed to record audio");
+                self.recording = None;
+            }
+
+            self.play_audio();
+        }
+
+        if let Some(wav) = self.recording.take() {
+            if let Err(e) = wav.finish() {
+                println!("Failed to save recording: {}", e);
+            }
+        }
+    }
+}
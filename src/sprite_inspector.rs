@@ -0,0 +1,130 @@
+use std::fmt;
+
+use addressable;
+use cartridge::Cartridge;
+use nes::NesWithCartridge;
+use frame_buffer::FrameBuffer;
+use renderer::Colour;
+use ppu::{Sprite, NUM_SPRITES, SPRITES_PER_LINE, DISPLAY_HEIGHT};
+
+// sprite graphics are laid out in a grid of fixed-size cells
+pub const SHEET_COLUMNS: usize = 8;
+pub const SHEET_ROWS: usize = NUM_SPRITES / SHEET_COLUMNS;
+pub const SHEET_CELL_WIDTH: usize = 16;
+pub const SHEET_CELL_HEIGHT: usize = 16;
+pub const SHEET_WIDTH: usize = SHEET_COLUMNS * SHEET_CELL_WIDTH;
+pub const SHEET_HEIGHT: usize = SHEET_ROWS * SHEET_CELL_HEIGHT;
+
+const SHEET_BACKGROUND_COLOUR: Colour = 0x0f;
+
+pub struct SpriteEntry {
+    pub index: usize,
+    pub sprite: Sprite,
+    pub height: usize,
+    pub off_screen: bool,
+    // number of scanlines on which this sprite would not be drawn
+    // because 8 earlier sprites already occupy the line
+    pub dropped_lines: usize,
+}
+
+impl SpriteEntry {
+    pub fn dropped(&self) -> bool {
+        self.dropped_lines > 0
+    }
+
+    pub fn sheet_position(&self) -> (usize, usize) {
+        ((self.index % SHEET_COLUMNS) * SHEET_CELL_WIDTH,
+         (self.index / SHEET_COLUMNS) * SHEET_CELL_HEIGHT)
+    }
+}
+
+impl fmt::Display for SpriteEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{:2}: x: {:3} y: {:3} tile: 0x{:02x} palette: {} ",
+                    self.index, self.sprite.x, self.sprite.y, self.sprite.index, self.sprite.palette));
+        try!(write!(f, "{} ", if self.sprite.priority { "back " } else { "front" }));
+        try!(write!(f, "{}", if self.sprite.horizontal_flip { "H" } else { "-" }));
+        try!(write!(f, "{}", if self.sprite.vertical_flip { "V" } else { "-" }));
+        if self.off_screen {
+            try!(write!(f, " off-screen"));
+        }
+        if self.dropped() {
+            try!(write!(f, " dropped on {} lines", self.dropped_lines));
+        }
+        Ok(())
+    }
+}
+
+pub struct SpriteInspector {
+    entries: Vec<SpriteEntry>,
+    sheet: FrameBuffer,
+}
+
+impl SpriteInspector {
+    pub fn new() -> Self {
+        SpriteInspector {
+            entries: Vec::new(),
+            sheet: FrameBuffer::new(SHEET_WIDTH, SHEET_HEIGHT),
+        }
+    }
+
+    pub fn update<C: Cartridge>(&mut self, nes: &mut NesWithCartridge<C>) -> addressable::Result<()> {
+        let height = nes.ppu.sprite_height();
+        let mut sprites_on_line = vec![0; DISPLAY_HEIGHT];
+
+        self.entries.clear();
+        self.sheet.clear(SHEET_BACKGROUND_COLOUR);
+
+        for i in 0..NUM_SPRITES {
+            let sprite = nes.ppu.sprite(i);
+            let off_screen = !sprite.is_visible();
+
+            // sprites are evaluated in OAM order, so earlier sprites
+            // take the available slots on each line
+            let mut dropped_lines = 0;
+            if !off_screen {
+                let top = sprite.y as usize;
+                let bottom = (top + height).min(DISPLAY_HEIGHT);
+                for count in sprites_on_line[top..bottom].iter_mut() {
+                    if *count < SPRITES_PER_LINE {
+                        *count += 1;
+                    } else {
+                        dropped_lines += 1;
+                    }
+                }
+            }
+
+            let entry = SpriteEntry {
+                index: i,
+                sprite: sprite,
+                height: height,
+                off_screen: off_screen,
+                dropped_lines: dropped_lines,
+            };
+
+            let (x, y) = entry.sheet_position();
+            try!(nes.render_sprite_graphic(&mut self.sheet, i, x as u8, y as u8));
+
+            self.entries.push(entry);
+        }
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[SpriteEntry] {
+        &self.entries
+    }
+
+    pub fn sheet(&self) -> &FrameBuffer {
+        &self.sheet
+    }
+}
+
+impl fmt::Display for SpriteInspector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in self.entries.iter() {
+            try!(writeln!(f, "{}", entry));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use addressable::Address;
+
+// The register select and data ports, as decoded by mapper 69
+pub const REGISTER_SELECT: Address = 0xc000;
+pub const REGISTER_WRITE: Address = 0xe000;
+
+const REGISTER_SELECT_MASK: u8 = mask!(4);
+
+// Internal registers
+const TONE_PERIOD_LOW_A: usize = 0x0;
+const TONE_PERIOD_HIGH_C: usize = 0x5;
+const NOISE_PERIOD: usize = 0x6;
+const NOISE_TONE_DISABLE: usize = 0x7;
+const VOLUME_A: usize = 0x8;
+const VOLUME_C: usize = 0xa;
+const ENVELOPE_PERIOD_LOW: usize = 0xb;
+const ENVELOPE_PERIOD_HIGH: usize = 0xc;
+const ENVELOPE_SHAPE: usize = 0xd;
+
+const TONE_PERIOD_HIGH_MASK: u8 = mask!(4);
+const NOISE_PERIOD_MASK: u8 = mask!(5);
+const NOISE_DISABLE_SHIFT: usize = 3;
+const VOLUME_MASK: u8 = mask!(4);
+const VOLUME_ENVELOPE: u8 = bit!(4);
+
+const ENVELOPE_HOLD: u8 = bit!(0);
+const ENVELOPE_ALTERNATE: u8 = bit!(1);
+const ENVELOPE_ATTACK: u8 = bit!(2);
+const ENVELOPE_CONTINUE: u8 = bit!(3);
+
+const NUM_CHANNELS: usize = 3;
+
+// The chip's generators advance once every 16 CPU cycles
+const CLOCK_DIVIDER: u8 = 16;
+
+const ENVELOPE_STEPS: u8 = 32;
+const MAX_LEVEL: u8 = ENVELOPE_STEPS - 1;
+
+// Each level is 1.5dB louder than the last
+const DECIBELS_PER_LEVEL: f32 = 1.5;
+
+// A channel at full volume is about 6dB louder than an APU pulse at full
+// volume
+const MAX_AMPLITUDE: f32 = 0.3;
+
+struct Tone {
+    period: u16,
+    counter: u16,
+    output: bool,
+}
+
+impl Tone {
+    fn new() -> Self {
+        Tone {
+            period: 0,
+            counter: 0,
+            output: false,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period {
+            self.counter = 0;
+            self.output = !self.output;
+        }
+    }
+}
+
+struct Noise {
+    period: u8,
+    counter: u8,
+    shift_register: u32,
+    // the noise runs at half the rate of the tones
+    half: bool,
+}
+
+impl Noise {
+    fn new() -> Self {
+        Noise {
+            period: 0,
+            counter: 0,
+            shift_register: 1,
+            half: false,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.half = !self.half;
+        if self.half {
+            return;
+        }
+        self.counter += 1;
+        if self.counter >= self.period {
+            self.counter = 0;
+            // 17 bit LFSR with taps at bits 0 and 3
+            let feedback = (self.shift_register ^ (self.shift_register >> 3)) & 1;
+            self.shift_register = (self.shift_register >> 1) | (feedback << 16);
+        }
+    }
+
+    fn output(&self) -> bool {
+        self.shift_register & 1 != 0
+    }
+}
+
+struct Envelope {
+    period: u16,
+    counter: u16,
+    shape: u8,
+    step: u8,
+    attack: bool,
+    holding: bool,
+}
+
+impl Envelope {
+    fn new() -> Self {
+        Envelope {
+            period: 0,
+            counter: 0,
+            shape: 0,
+            step: 0,
+            attack: false,
+            holding: false,
+        }
+    }
+
+    fn write_shape(&mut self, data: u8) {
+        self.shape = data;
+        self.counter = 0;
+        self.step = 0;
+        self.attack = data & ENVELOPE_ATTACK != 0;
+        self.holding = false;
+    }
+
+    fn clock(&mut self) {
+        if self.holding {
+            return;
+        }
+        self.counter += 1;
+        if self.counter < self.period {
+            return;
+        }
+        self.counter = 0;
+
+        if self.step < MAX_LEVEL {
+            self.step += 1;
+            return;
+        }
+
+        // the end of a ramp
+        if self.shape & ENVELOPE_CONTINUE == 0 {
+            self.holding = true;
+            self.attack = false;
+        } else if self.shape & ENVELOPE_HOLD != 0 {
+            self.holding = true;
+            if self.shape & ENVELOPE_ALTERNATE != 0 {
+                self.attack = !self.attack;
+            }
+        } else {
+            if self.shape & ENVELOPE_ALTERNATE != 0 {
+                self.attack = !self.attack;
+            }
+            self.step = 0;
+        }
+    }
+
+    fn level(&self) -> u8 {
+        if self.attack {
+            self.step
+        } else {
+            MAX_LEVEL - self.step
+        }
+    }
+}
+
+// Sunsoft's 5B, a YM2149F with three square wave channels, shared noise
+// and envelope generators, and logarithmic volume
+pub struct Sunsoft5bAudio {
+    selected: usize,
+    tones: [Tone; NUM_CHANNELS],
+    noise: Noise,
+    envelope: Envelope,
+    tone_disabled: [bool; NUM_CHANNELS],
+    noise_disabled: [bool; NUM_CHANNELS],
+    volumes: [u8; NUM_CHANNELS],
+    divider: u8,
+    amplitudes: [f32; ENVELOPE_STEPS as usize],
+}
+
+impl Sunsoft5bAudio {
+    pub fn new() -> Self {
+        let mut amplitudes = [0.0; ENVELOPE_STEPS as usize];
+        for (level, amplitude) in amplitudes.iter_mut().enumerate().skip(1) {
+            let decibels = (level as f32 - MAX_LEVEL as f32) * DECIBELS_PER_LEVEL;
+            *amplitude = MAX_AMPLITUDE * 10.0f32.powf(decibels / 20.0);
+        }
+
+        Sunsoft5bAudio {
+            selected: 0,
+            tones: [Tone::new(), Tone::new(), Tone::new()],
+            noise: Noise::new(),
+            envelope: Envelope::new(),
+            tone_disabled: [true; NUM_CHANNELS],
+            noise_disabled: [true; NUM_CHANNELS],
+            volumes: [0; NUM_CHANNELS],
+            divider: 0,
+            amplitudes: amplitudes,
+        }
+    }
+
+    pub fn write(&mut self, port: Address, data: u8) {
+        match port {
+            REGISTER_SELECT => self.selected = (data & REGISTER_SELECT_MASK) as usize,
+            REGISTER_WRITE => {
+                let register = self.selected;
+                self.write_register(register, data);
+            }
+            _ => {}
+        }
+    }
+
+    fn write_register(&mut self, register: usize, data: u8) {
+        match register {
+            TONE_PERIOD_LOW_A...TONE_PERIOD_HIGH_C => {
+                let tone = &mut self.tones[register / 2];
+                tone.period = if register % 2 == 0 {
+                    (tone.period & 0xf00) | data as u16
+                } else {
+                    (tone.period & 0xff) | (((data & TONE_PERIOD_HIGH_MASK) as u16) << 8)
+                };
+            }
+            NOISE_PERIOD => self.noise.period = data & NOISE_PERIOD_MASK,
+            NOISE_TONE_DISABLE => {
+                for channel in 0..NUM_CHANNELS {
+                    self.tone_disabled[channel] = data & bit!(channel) != 0;
+                    self.noise_disabled[channel] = data & bit!(channel + NOISE_DISABLE_SHIFT) != 0;
+                }
+            }
+            VOLUME_A...VOLUME_C => self.volumes[register - VOLUME_A] = data,
+            ENVELOPE_PERIOD_LOW => {
+                self.envelope.period = (self.envelope.period & 0xff00) | data as u16;
+            }
+            ENVELOPE_PERIOD_HIGH => {
+                self.envelope.period = (self.envelope.period & 0xff) | ((data as u16) << 8);
+            }
+            ENVELOPE_SHAPE => self.envelope.write_shape(data),
+            // the I/O ports aren't connected
+            _ => {}
+        }
+    }
+
+    // Clocks the chip by one CPU cycle
+    pub fn tick(&mut self) {
+        self.divider += 1;
+        if self.divider < CLOCK_DIVIDER {
+            return;
+        }
+        self.divider = 0;
+
+        for tone in self.tones.iter_mut() {
+            tone.clock();
+        }
+        self.noise.clock();
+        self.envelope.clock();
+    }
+
+    pub fn output(&self) -> f32 {
+        let mut output = 0.0;
+        for channel in 0..NUM_CHANNELS {
+            let tone = self.tones[channel].output || self.tone_disabled[channel];
+            let noise = self.noise.output() || self.noise_disabled[channel];
+            if !(tone && noise) {
+                continue;
+            }
+
+            let volume = self.volumes[channel];
+            let level = if volume & VOLUME_ENVELOPE != 0 {
+                self.envelope.level()
+            } else if volume & VOLUME_MASK == 0 {
+                0
+            } else {
+                (volume & VOLUME_MASK) * 2 + 1
+            };
+            output += self.amplitudes[level as usize];
+        }
+        output
+    }
+}
@@ -0,0 +1,84 @@
+use addressable::{PpuAddressable, Address, Result};
+use cartridge::PATTERN_TABLE_END;
+use ppu::{TILE_WIDTH, TILE_HEIGHT, PATTERN_TABLE_ENTRY_BYTES};
+
+const NUM_TILES: usize = (PATTERN_TABLE_END as usize + 1) / PATTERN_TABLE_ENTRY_BYTES as usize;
+
+// The 2-bit palette index of each pixel of a tile, left to right
+pub type TileRow = [u8; TILE_WIDTH as usize];
+pub type DecodedTile = [TileRow; TILE_HEIGHT as usize];
+
+// Caches the pattern table as decoded tiles, so the renderer doesn't need to
+// combine the two bit planes of a tile every time it's drawn.
+pub struct TileCache {
+    tiles: Vec<DecodedTile>,
+    valid: Vec<bool>,
+    // the memory's pattern table generation when the cache was last filled
+    generation: usize,
+}
+
+impl TileCache {
+    pub fn new() -> Self {
+        TileCache {
+            tiles: vec![[[0; TILE_WIDTH as usize]; TILE_HEIGHT as usize]; NUM_TILES],
+            valid: vec![false; NUM_TILES],
+            generation: 0,
+        }
+    }
+
+    pub fn invalidate_all(&mut self) {
+        for valid in self.valid.iter_mut() {
+            *valid = false;
+        }
+    }
+
+    // Invalidates the tile containing a pattern table byte that was written
+    pub fn invalidate(&mut self, address: Address) {
+        if address <= PATTERN_TABLE_END {
+            self.valid[Self::tile_number(address)] = false;
+        }
+    }
+
+    // Discards everything if the memory's banks have been switched since the
+    // cache was filled
+    pub fn sync<M: PpuAddressable>(&mut self, memory: &M) {
+        let generation = memory.pattern_table_generation();
+        if generation != self.generation {
+            self.invalidate_all();
+            self.generation = generation;
+        }
+    }
+
+    pub fn tile<M: PpuAddressable>(&mut self, memory: &mut M, pt_address: Address) -> Result<DecodedTile> {
+        let number = Self::tile_number(pt_address);
+
+        if !self.valid[number] {
+            self.tiles[number] = try!(Self::decode(memory, pt_address));
+            self.valid[number] = true;
+        }
+
+        Ok(self.tiles[number])
+    }
+
+    fn tile_number(address: Address) -> usize {
+        address as usize / PATTERN_TABLE_ENTRY_BYTES as usize
+    }
+
+    fn decode<M: PpuAddressable>(memory: &mut M, pt_address: Address) -> Result<DecodedTile> {
+        let mut tile = [[0; TILE_WIDTH as usize]; TILE_HEIGHT as usize];
+
+        for (i, row) in tile.iter_mut().enumerate() {
+            let i = i as Address;
+            let plane_0 = try!(memory.ppu_read8(pt_address + i));
+            let plane_1 = try!(memory.ppu_read8(pt_address + TILE_HEIGHT + i));
+
+            // the most significant bit is the leftmost pixel
+            for (j, pixel) in row.iter_mut().enumerate() {
+                let shift = TILE_WIDTH as usize - 1 - j;
+                *pixel = ((plane_0 >> shift) & bit!(0)) | (((plane_1 >> shift) & bit!(0)) << 1);
+            }
+        }
+
+        Ok(tile)
+    }
+}
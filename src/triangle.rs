@@ -0,0 +1,116 @@
+use length_counter::LengthCounter;
+
+const LINEAR_COUNTER: usize = 0;
+const TIMER_LOW: usize = 2;
+const LENGTH_TIMER_HIGH: usize = 3;
+
+// also halts the length counter
+const LINEAR_COUNTER_CONTROL: u8 = bit!(7);
+const LINEAR_COUNTER_RELOAD_MASK: u8 = mask!(7);
+
+const TIMER_HIGH_MASK: u8 = mask!(3);
+const LENGTH_INDEX_SHIFT: usize = 3;
+
+// Periods this short produce a tone far above hearing, which the console's
+// filters reduce to the middle of the waveform
+const ULTRASONIC_PERIOD: u16 = 2;
+const ULTRASONIC_LEVEL: u8 = 7;
+
+const SEQUENCE_LENGTH: usize = 32;
+
+const SEQUENCE: [u8; SEQUENCE_LENGTH] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+pub struct Triangle {
+    sequence_position: usize,
+    timer_period: u16,
+    timer: u16,
+    length_counter: LengthCounter,
+    control: bool,
+    linear_counter_reload: u8,
+    linear_counter: u8,
+    linear_counter_reload_flag: bool,
+}
+
+impl Triangle {
+    pub fn new() -> Self {
+        Triangle {
+            sequence_position: 0,
+            timer_period: 0,
+            timer: 0,
+            length_counter: LengthCounter::new(),
+            control: false,
+            linear_counter_reload: 0,
+            linear_counter: 0,
+            linear_counter_reload_flag: false,
+        }
+    }
+
+    // Writes one of the channel's 4 registers, the second of which is unused
+    pub fn write(&mut self, register: usize, data: u8) {
+        match register {
+            LINEAR_COUNTER => {
+                self.control = data & LINEAR_COUNTER_CONTROL != 0;
+                self.length_counter.set_halt(self.control);
+                self.linear_counter_reload = data & LINEAR_COUNTER_RELOAD_MASK;
+            }
+            TIMER_LOW => {
+                self.timer_period = (self.timer_period & 0xff00) | data as u16;
+            }
+            LENGTH_TIMER_HIGH => {
+                self.timer_period = (self.timer_period & 0x00ff) | (((data & TIMER_HIGH_MASK) as u16) << 8);
+                self.length_counter.load(data >> LENGTH_INDEX_SHIFT);
+                self.linear_counter_reload_flag = true;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn active(&self) -> bool {
+        self.length_counter.active()
+    }
+
+    // Clocked every CPU cycle. The sequencer only advances while both
+    // counters are non-zero, so a silenced triangle holds its level.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            if self.linear_counter > 0 && self.length_counter.active() {
+                self.sequence_position = (self.sequence_position + 1) % SEQUENCE_LENGTH;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_counter_reload_flag {
+            self.linear_counter = self.linear_counter_reload;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_counter_reload_flag = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    // Current output level, from 0 to 15
+    pub fn output(&self) -> u8 {
+        if self.timer_period < ULTRASONIC_PERIOD && self.linear_counter > 0 && self.length_counter.active() {
+            ULTRASONIC_LEVEL
+        } else {
+            SEQUENCE[self.sequence_position]
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use input::InputDevice;
+use controller::Controller;
+
+// The NES model is read from $4017 alone
+const NES_FIRE: u8 = bit!(3);
+const NES_DATA_SHIFT: usize = 4;
+// The Famicom model plugs into the expansion port, and is read on bit 1
+// beside the pads: its button on $4016, and the knob on $4017
+const FAMICOM_FIRE: u8 = bit!(1);
+const FAMICOM_DATA_SHIFT: usize = 1;
+
+// The range the knob turns through, as read by Arkanoid
+const MIN_POSITION: f32 = 98.0;
+const MAX_POSITION: f32 = 242.0;
+
+pub const DEFAULT_SENSITIVITY: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VausModel {
+    Nes,
+    Famicom,
+}
+
+// Arkanoid's paddle: a knob read as an 8-bit value, and a fire button. The
+// knob's value is latched by the strobe and read a bit at a time, most
+// significant first and inverted. The knob is turned by moving the mouse
+// left and right, by `sensitivity` per pixel.
+//
+// The Famicom model is in both ports, one half for each, beside that
+// port's pad.
+pub struct Vaus {
+    model: VausModel,
+    port: usize,
+    position: f32,
+    sensitivity: f32,
+    fire: bool,
+    shift_register: u8,
+    strobe: bool,
+    pad: Controller,
+}
+
+impl Vaus {
+    pub fn new(model: VausModel, port: usize, sensitivity: f32) -> Self {
+        Vaus {
+            model: model,
+            port: port,
+            position: (MIN_POSITION + MAX_POSITION) / 2.0,
+            sensitivity: sensitivity,
+            fire: false,
+            shift_register: 0,
+            strobe: false,
+            pad: Controller::new(),
+        }
+    }
+
+    fn reload(&mut self) {
+        self.shift_register = !(self.position as u8);
+    }
+
+    // The next bit of the knob's value
+    fn read_knob(&mut self) -> u8 {
+        if self.strobe {
+            self.reload();
+        }
+        let data = self.shift_register >> 7;
+        self.shift_register <<= 1;
+        data
+    }
+}
+
+impl InputDevice for Vaus {
+    fn write_strobe(&mut self, strobe: bool) {
+        if strobe || self.strobe {
+            self.reload();
+        }
+        self.strobe = strobe;
+        self.pad.write_strobe(strobe);
+    }
+
+    fn read(&mut self) -> u8 {
+        match self.model {
+            VausModel::Nes => {
+                let fire = if self.fire { NES_FIRE } else { 0 };
+                fire | self.read_knob() << NES_DATA_SHIFT
+            }
+            VausModel::Famicom => {
+                let pad = self.pad.read() & bit!(0);
+                if self.port == 0 {
+                    pad | if self.fire { FAMICOM_FIRE } else { 0 }
+                } else {
+                    pad | self.read_knob() << FAMICOM_DATA_SHIFT
+                }
+            }
+        }
+    }
+
+    // The Famicom's pads are still there
+    fn set_buttons(&mut self, pad: usize, buttons: u8) {
+        if self.model == VausModel::Famicom {
+            self.pad.set_buttons(pad, buttons);
+        }
+    }
+
+    fn move_pointer(&mut self, dx: i32) {
+        self.position = (self.position + dx as f32 * self.sensitivity).max(MIN_POSITION).min(MAX_POSITION);
+    }
+
+    fn set_fire(&mut self, pressed: bool) {
+        self.fire = pressed;
+    }
+}
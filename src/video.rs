@@ -0,0 +1,230 @@
+use frame_buffer::{FrameBuffer, RgbBuffer};
+use rgb_palette::RgbPalette;
+use ntsc::{NtscFilter, NtscSettings};
+use scaler::PostProcess;
+use image::TvSystem;
+use config::Config;
+use ppu;
+
+const VIDEO_SECTION: &'static str = "video";
+
+// NTSC TVs typically hid around 8 lines at the top and bottom of the picture
+const NTSC_OVERSCAN_LINES: usize = 8;
+
+// Width of a pixel relative to its height, as displayed on a TV
+const NTSC_PIXEL_ASPECT: f32 = 8.0 / 7.0;
+const PAL_PIXEL_ASPECT: f32 = 2950000.0 / 2128137.0;
+
+// Number of pixels hidden at each edge of the picture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overscan {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl Overscan {
+    pub fn none() -> Self {
+        Overscan {
+            top: 0,
+            bottom: 0,
+            left: 0,
+            right: 0,
+        }
+    }
+
+    pub fn for_tv_system(tv_system: &TvSystem) -> Self {
+        match *tv_system {
+            TvSystem::Ntsc => {
+                Overscan {
+                    top: NTSC_OVERSCAN_LINES,
+                    bottom: NTSC_OVERSCAN_LINES,
+                    ..Self::none()
+                }
+            }
+            TvSystem::Pal => Self::none(),
+        }
+    }
+
+    // Parses "TOP,BOTTOM,LEFT,RIGHT"
+    pub fn parse(s: &str) -> Option<Self> {
+        let edges: Vec<usize> = match s.split(',').map(|e| e.trim().parse()).collect() {
+            Ok(edges) => edges,
+            Err(_) => return None,
+        };
+        if edges.len() != 4 {
+            return None;
+        }
+
+        Some(Overscan {
+            top: edges[0],
+            bottom: edges[1],
+            left: edges[2],
+            right: edges[3],
+        })
+    }
+
+    // Overrides edges with any overscan_* keys in the config's [video]
+    // section, then in the section for the TV system, e.g. [video.pal]
+    pub fn apply_config(&mut self, config: &Config, tv_system: &TvSystem) {
+        for &section in [VIDEO_SECTION, region_section(tv_system)].iter() {
+            let edge = |key, default: usize| {
+                config.get_integer(section, key).map_or(default, |value| value.max(0) as usize)
+            };
+
+            *self = Overscan {
+                top: edge("overscan_top", self.top),
+                bottom: edge("overscan_bottom", self.bottom),
+                left: edge("overscan_left", self.left),
+                right: edge("overscan_right", self.right),
+            };
+        }
+    }
+
+    // Whether any of the picture is left once cropped
+    pub fn is_valid(&self) -> bool {
+        self.left + self.right < ppu::DISPLAY_WIDTH && self.top + self.bottom < ppu::DISPLAY_HEIGHT
+    }
+}
+
+// The config section for settings which only apply to one TV system
+pub fn region_section(tv_system: &TvSystem) -> &'static str {
+    match *tv_system {
+        TvSystem::Ntsc => "video.ntsc",
+        TvSystem::Pal => "video.pal",
+    }
+}
+
+// A [video] setting, taken from the TV system's section if it's there
+pub fn config_bool(config: &Config, tv_system: &TvSystem, key: &str) -> Option<bool> {
+    config.get_bool(region_section(tv_system), key).or(config.get_bool(VIDEO_SECTION, key))
+}
+
+pub fn pixel_aspect(tv_system: &TvSystem) -> f32 {
+    match *tv_system {
+        TvSystem::Ntsc => NTSC_PIXEL_ASPECT,
+        TvSystem::Pal => PAL_PIXEL_ASPECT,
+    }
+}
+
+pub struct VideoOptions {
+    pub palette: RgbPalette,
+    pub ntsc: Option<NtscSettings>,
+    pub post_process: PostProcess,
+    // size of the window relative to the NES display
+    pub scale: usize,
+    pub overscan: Overscan,
+    // when set, the picture is stretched horizontally by this ratio
+    pub pixel_aspect: Option<f32>,
+}
+
+// Converts the PPU's frame of NES colours into the final RGB picture,
+// shared by every frontend so that the window and screenshots match.
+pub struct VideoPipeline {
+    palette: RgbPalette,
+    ntsc: Option<NtscFilter>,
+    ntsc_settings: NtscSettings,
+    post_process: PostProcess,
+    overscan: Overscan,
+    pixel_aspect: Option<f32>,
+    cropped: FrameBuffer,
+    rgb: RgbBuffer,
+    scaled: RgbBuffer,
+    output: RgbBuffer,
+}
+
+impl VideoPipeline {
+    pub fn new(options: VideoOptions) -> Self {
+        VideoPipeline {
+            palette: options.palette,
+            ntsc: options.ntsc.map(NtscFilter::new),
+            ntsc_settings: options.ntsc.unwrap_or(NtscSettings::composite()),
+            post_process: options.post_process,
+            overscan: options.overscan,
+            pixel_aspect: options.pixel_aspect,
+            cropped: FrameBuffer::new(0, 0),
+            rgb: RgbBuffer::new(0, 0),
+            scaled: RgbBuffer::new(0, 0),
+            output: RgbBuffer::new(0, 0),
+        }
+    }
+
+    pub fn palette(&self) -> &RgbPalette {
+        &self.palette
+    }
+
+    pub fn overscan(&self) -> Overscan {
+        self.overscan
+    }
+
+    pub fn toggle_ntsc(&mut self) {
+        self.ntsc = match self.ntsc {
+            Some(_) => None,
+            None => Some(NtscFilter::new(self.ntsc_settings)),
+        };
+    }
+
+    // Size of the visible picture, in NES pixel heights, for a frame of
+    // the given size
+    pub fn display_size(&self, frame_width: usize, frame_height: usize) -> (usize, usize) {
+        let width = frame_width.saturating_sub(self.overscan.left + self.overscan.right);
+        let height = frame_height.saturating_sub(self.overscan.top + self.overscan.bottom);
+
+        match self.pixel_aspect {
+            Some(aspect) => ((width as f32 * aspect).round() as usize, height),
+            None => (width, height),
+        }
+    }
+
+    pub fn process(&mut self, frame: &FrameBuffer) -> &RgbBuffer {
+        let width = frame.width().saturating_sub(self.overscan.left + self.overscan.right);
+        let height = frame.height().saturating_sub(self.overscan.top + self.overscan.bottom);
+        frame.crop(self.overscan.left, self.overscan.top, width, height, &mut self.cropped);
+
+        match self.ntsc {
+            Some(ref mut ntsc) => ntsc.apply(&self.cropped, &mut self.rgb),
+            None => self.palette.apply(&self.cropped, &mut self.rgb),
+        }
+
+        self.post_process.apply(&self.rgb, &mut self.scaled);
+
+        let (display_width, display_height) = self.display_size(frame.width(), frame.height());
+        let output_width = display_width * self.scaled.height() / display_height.max(1);
+        resample_horizontal(&self.scaled, &mut self.output, output_width);
+
+        &self.output
+    }
+
+    pub fn output(&self) -> &RgbBuffer {
+        &self.output
+    }
+}
+
+// Stretches an image horizontally with linear interpolation
+fn resample_horizontal(input: &RgbBuffer, output: &mut RgbBuffer, width: usize) {
+    if width == input.width() {
+        output.clone_from(input);
+        return;
+    }
+
+    output.resize(width, input.height());
+    if input.width() == 0 || width == 0 {
+        return;
+    }
+
+    let ratio = input.width() as f32 / width as f32;
+    for x in 0..width {
+        let source = ((x as f32 + 0.5) * ratio - 0.5).max(0.0);
+        let left = (source as usize).min(input.width() - 1);
+        let right = (left + 1).min(input.width() - 1);
+        let t = source - left as f32;
+
+        for y in 0..input.height() {
+            let (r0, g0, b0) = input.get_pixel(left, y);
+            let (r1, g1, b1) = input.get_pixel(right, y);
+            let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+            output.set_pixel(x, y, (lerp(r0, r1), lerp(g0, g1), lerp(b0, b1)));
+        }
+    }
+}
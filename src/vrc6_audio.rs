@@ -0,0 +1,220 @@
+use addressable::Address;
+
+// Registers, as the address with the bank bits of mapper 24 ($9000-$b002)
+pub const PULSE_1_CONTROL: Address = 0x9000;
+pub const PULSE_1_PERIOD_LOW: Address = 0x9001;
+pub const PULSE_1_PERIOD_HIGH: Address = 0x9002;
+pub const FREQUENCY_CONTROL: Address = 0x9003;
+pub const PULSE_2_CONTROL: Address = 0xa000;
+pub const PULSE_2_PERIOD_LOW: Address = 0xa001;
+pub const PULSE_2_PERIOD_HIGH: Address = 0xa002;
+pub const SAW_RATE: Address = 0xb000;
+pub const SAW_PERIOD_LOW: Address = 0xb001;
+pub const SAW_PERIOD_HIGH: Address = 0xb002;
+
+const PULSE_MODE: u8 = bit!(7);
+const PULSE_DUTY_SHIFT: usize = 4;
+const PULSE_DUTY_MASK: u8 = mask!(3);
+const PULSE_VOLUME_MASK: u8 = mask!(4);
+
+const PERIOD_HIGH_ENABLE: u8 = bit!(7);
+const PERIOD_HIGH_MASK: u8 = mask!(4);
+
+const FREQUENCY_HALT: u8 = bit!(0);
+const FREQUENCY_SHIFT_4: u8 = bit!(1);
+const FREQUENCY_SHIFT_8: u8 = bit!(2);
+
+const SAW_RATE_MASK: u8 = mask!(6);
+const SAW_STEPS: u8 = 14;
+const SAW_OUTPUT_SHIFT: usize = 3;
+
+const PULSE_STEPS: u8 = 16;
+
+// A VRC6 pulse at full volume is about as loud as an APU pulse at full
+// volume, and the chip's DAC is linear
+const LEVEL_PER_STEP: f32 = 0.00996;
+
+struct Vrc6Pulse {
+    mode: bool,
+    duty: u8,
+    volume: u8,
+    enabled: bool,
+    period: u16,
+    timer: u16,
+    step: u8,
+}
+
+impl Vrc6Pulse {
+    fn new() -> Self {
+        Vrc6Pulse {
+            mode: false,
+            duty: 0,
+            volume: 0,
+            enabled: false,
+            period: 0,
+            timer: 0,
+            step: PULSE_STEPS - 1,
+        }
+    }
+
+    fn write_control(&mut self, data: u8) {
+        self.mode = data & PULSE_MODE != 0;
+        self.duty = (data >> PULSE_DUTY_SHIFT) & PULSE_DUTY_MASK;
+        self.volume = data & PULSE_VOLUME_MASK;
+    }
+
+    fn write_period_low(&mut self, data: u8) {
+        self.period = (self.period & 0xf00) | data as u16;
+    }
+
+    fn write_period_high(&mut self, data: u8) {
+        self.period = (self.period & 0xff) | (((data & PERIOD_HIGH_MASK) as u16) << 8);
+        self.enabled = data & PERIOD_HIGH_ENABLE != 0;
+        // disabling the channel resets its duty cycle
+        if !self.enabled {
+            self.step = PULSE_STEPS - 1;
+        }
+    }
+
+    fn clock(&mut self, shift: usize) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = if self.step == 0 { PULSE_STEPS - 1 } else { self.step - 1 };
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled && (self.mode || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+struct Vrc6Saw {
+    rate: u8,
+    enabled: bool,
+    period: u16,
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Saw {
+    fn new() -> Self {
+        Vrc6Saw {
+            rate: 0,
+            enabled: false,
+            period: 0,
+            timer: 0,
+            step: 0,
+            accumulator: 0,
+        }
+    }
+
+    fn write_period_low(&mut self, data: u8) {
+        self.period = (self.period & 0xf00) | data as u16;
+    }
+
+    fn write_period_high(&mut self, data: u8) {
+        self.period = (self.period & 0xff) | (((data & PERIOD_HIGH_MASK) as u16) << 8);
+        self.enabled = data & PERIOD_HIGH_ENABLE != 0;
+        if !self.enabled {
+            self.step = 0;
+            self.accumulator = 0;
+        }
+    }
+
+    fn clock(&mut self, shift: usize) {
+        if !self.enabled {
+            return;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.period >> shift;
+
+        // the accumulator is added to on every other step, 6 times in all,
+        // and reset on the 14th
+        self.step += 1;
+        if self.step == SAW_STEPS {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step % 2 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> SAW_OUTPUT_SHIFT
+    }
+}
+
+// Konami's VRC6 sound: two pulse channels with 8 duty cycles and a sawtooth
+pub struct Vrc6Audio {
+    pulse_1: Vrc6Pulse,
+    pulse_2: Vrc6Pulse,
+    saw: Vrc6Saw,
+    halt: bool,
+    // how far right the periods are shifted, speeding up every channel
+    shift: usize,
+}
+
+impl Vrc6Audio {
+    pub fn new() -> Self {
+        Vrc6Audio {
+            pulse_1: Vrc6Pulse::new(),
+            pulse_2: Vrc6Pulse::new(),
+            saw: Vrc6Saw::new(),
+            halt: false,
+            shift: 0,
+        }
+    }
+
+    pub fn write(&mut self, register: Address, data: u8) {
+        match register {
+            PULSE_1_CONTROL => self.pulse_1.write_control(data),
+            PULSE_1_PERIOD_LOW => self.pulse_1.write_period_low(data),
+            PULSE_1_PERIOD_HIGH => self.pulse_1.write_period_high(data),
+            FREQUENCY_CONTROL => {
+                self.halt = data & FREQUENCY_HALT != 0;
+                self.shift = if data & FREQUENCY_SHIFT_8 != 0 {
+                    8
+                } else if data & FREQUENCY_SHIFT_4 != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            PULSE_2_CONTROL => self.pulse_2.write_control(data),
+            PULSE_2_PERIOD_LOW => self.pulse_2.write_period_low(data),
+            PULSE_2_PERIOD_HIGH => self.pulse_2.write_period_high(data),
+            SAW_RATE => self.saw.rate = data & SAW_RATE_MASK,
+            SAW_PERIOD_LOW => self.saw.write_period_low(data),
+            SAW_PERIOD_HIGH => self.saw.write_period_high(data),
+            _ => {}
+        }
+    }
+
+    // Clocks the channels by one CPU cycle
+    pub fn tick(&mut self) {
+        if self.halt {
+            return;
+        }
+        self.pulse_1.clock(self.shift);
+        self.pulse_2.clock(self.shift);
+        self.saw.clock(self.shift);
+    }
+
+    pub fn output(&self) -> f32 {
+        let sum = self.pulse_1.output() + self.pulse_2.output() + self.saw.output();
+        sum as f32 * LEVEL_PER_STEP
+    }
+}
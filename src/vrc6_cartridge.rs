@@ -0,0 +1,298 @@
+use image::NesImage;
+use cartridge;
+use addressable;
+use addressable::{PpuAddressable, Address};
+use vram::NesVram;
+use mirror::Mirroring;
+use vrc6_audio::Vrc6Audio;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x400;
+const NUM_CHR_BANKS: usize = 8;
+const CHR_RAM_SIZE: usize = 0x2000;
+
+const LOWER_ROM_ADDRESS: Address = 0x8000;
+const UPPER_ROM_ADDRESS: Address = 0xc000;
+
+// Registers, as decoded by mapper 24. Mapper 26 swaps address lines A0 and
+// A1, which is undone before decoding.
+const REGISTER_MASK: Address = 0xf003;
+const PRG_SELECT_16K_START: Address = 0x8000;
+const PRG_SELECT_16K_END: Address = 0x8003;
+const AUDIO_REGISTERS_START: Address = 0x9000;
+const AUDIO_REGISTERS_END: Address = 0xb002;
+const PPU_BANKING_STYLE: Address = 0xb003;
+const PRG_SELECT_8K_START: Address = 0xc000;
+const PRG_SELECT_8K_END: Address = 0xc003;
+const CHR_SELECT_START: Address = 0xd000;
+const CHR_SELECT_END: Address = 0xe003;
+const IRQ_LATCH: Address = 0xf000;
+const IRQ_CONTROL: Address = 0xf001;
+const IRQ_ACKNOWLEDGE: Address = 0xf002;
+
+const PRG_SELECT_16K_MASK: u8 = mask!(4);
+const PRG_SELECT_8K_MASK: u8 = mask!(5);
+
+const MIRRORING_SHIFT: usize = 2;
+const MIRRORING_MASK: u8 = mask!(2);
+
+const IRQ_ENABLE_AFTER_ACKNOWLEDGE: u8 = bit!(0);
+const IRQ_ENABLE: u8 = bit!(1);
+const IRQ_CYCLE_MODE: u8 = bit!(2);
+
+// In scanline mode the counter is clocked every 113 2/3 CPU cycles, which
+// the prescaler counts in thirds
+const IRQ_PRESCALER_PERIOD: i16 = 341;
+const IRQ_PRESCALER_STEP: i16 = 3;
+
+// Konami's VRC6 IRQ counter, which counts up from its latch and raises an
+// IRQ when it overflows
+struct Vrc6Irq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enable_after_acknowledge: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl Vrc6Irq {
+    fn new() -> Self {
+        Vrc6Irq {
+            latch: 0,
+            counter: 0,
+            prescaler: IRQ_PRESCALER_PERIOD,
+            enabled: false,
+            enable_after_acknowledge: false,
+            cycle_mode: false,
+            pending: false,
+        }
+    }
+
+    fn write_control(&mut self, data: u8) {
+        self.pending = false;
+        self.enable_after_acknowledge = data & IRQ_ENABLE_AFTER_ACKNOWLEDGE != 0;
+        self.enabled = data & IRQ_ENABLE != 0;
+        self.cycle_mode = data & IRQ_CYCLE_MODE != 0;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = IRQ_PRESCALER_PERIOD;
+        }
+    }
+
+    fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enable_after_acknowledge;
+    }
+
+    fn tick(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.cycle_mode {
+            self.clock();
+        } else {
+            self.prescaler -= IRQ_PRESCALER_STEP;
+            if self.prescaler <= 0 {
+                self.prescaler += IRQ_PRESCALER_PERIOD;
+                self.clock();
+            }
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.counter == 0xff {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+// Konami's VRC6 (mappers 24 and 26): a 16KB and an 8KB switchable PRG bank
+// followed by the last 8KB, eight 1KB CHR banks, switchable mirroring, a
+// cycle or scanline IRQ and the VRC6 sound channels
+pub struct Vrc6Cartridge {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    swap_address_lines: bool,
+    prg_bank_16k: usize,
+    prg_bank_8k: usize,
+    chr_banks: [usize; NUM_CHR_BANKS],
+    // incremented on every CHR bank switch, for the PPU's tile cache
+    chr_generation: usize,
+    mirroring: Mirroring,
+    irq: Vrc6Irq,
+    audio: Vrc6Audio,
+}
+
+impl Vrc6Cartridge {
+    pub fn new(image: &NesImage) -> cartridge::Result<Self> {
+        let swap_address_lines = match image.header.mapper_number {
+            cartridge::VRC6A => false,
+            cartridge::VRC6B => true,
+            _ => return Err(cartridge::Error::IncorrectMapper),
+        };
+
+        if image.prg_rom.len() < PRG_BANK_SIZE || image.prg_rom.len() % PRG_BANK_SIZE != 0 {
+            return Err(cartridge::Error::InvalidRomSize);
+        }
+        if image.chr_rom.len() % CHR_BANK_SIZE != 0 {
+            return Err(cartridge::Error::InvalidChrRomSize);
+        }
+
+        let ram_size = image.header.prg_ram_size.unwrap_or(1).max(1);
+        let chr_is_ram = image.chr_rom.is_empty();
+        let chr = if chr_is_ram { vec![0; CHR_RAM_SIZE] } else { image.chr_rom.clone() };
+
+        Ok(Vrc6Cartridge {
+            prg_rom: image.prg_rom.clone(),
+            prg_ram: vec![0; cartridge::RAM_BANK_SIZE * ram_size],
+            chr: chr,
+            chr_is_ram: chr_is_ram,
+            swap_address_lines: swap_address_lines,
+            prg_bank_16k: 0,
+            prg_bank_8k: 0,
+            chr_banks: [0, 1, 2, 3, 4, 5, 6, 7],
+            chr_generation: 0,
+            mirroring: Mirroring::Vertical,
+            irq: Vrc6Irq::new(),
+            audio: Vrc6Audio::new(),
+        })
+    }
+
+    fn prg_read(&self, address: Address) -> u8 {
+        let num_banks = self.prg_rom.len() / PRG_BANK_SIZE;
+        let offset = (address - LOWER_ROM_ADDRESS) as usize;
+        let bank = match offset / PRG_BANK_SIZE {
+            0 => self.prg_bank_16k * 2,
+            1 => self.prg_bank_16k * 2 + 1,
+            2 => self.prg_bank_8k,
+            _ => num_banks - 1,
+        };
+        self.prg_rom[(bank % num_banks) * PRG_BANK_SIZE + offset % PRG_BANK_SIZE]
+    }
+
+    fn chr_offset(&self, address: Address) -> usize {
+        let num_banks = self.chr.len() / CHR_BANK_SIZE;
+        let bank = self.chr_banks[address as usize / CHR_BANK_SIZE] % num_banks;
+        bank * CHR_BANK_SIZE + address as usize % CHR_BANK_SIZE
+    }
+
+    // Writes anywhere in ROM reach a register
+    fn register_write(&mut self, address: Address, data: u8) {
+        let address = if self.swap_address_lines {
+            (address & !0x3) | ((address & 0x1) << 1) | ((address & 0x2) >> 1)
+        } else {
+            address
+        };
+
+        match address & REGISTER_MASK {
+            PRG_SELECT_16K_START...PRG_SELECT_16K_END => {
+                self.prg_bank_16k = (data & PRG_SELECT_16K_MASK) as usize;
+            }
+            PPU_BANKING_STYLE => {
+                self.mirroring = match (data >> MIRRORING_SHIFT) & MIRRORING_MASK {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            }
+            register @ AUDIO_REGISTERS_START...AUDIO_REGISTERS_END => self.audio.write(register, data),
+            PRG_SELECT_8K_START...PRG_SELECT_8K_END => {
+                self.prg_bank_8k = (data & PRG_SELECT_8K_MASK) as usize;
+            }
+            register @ CHR_SELECT_START...CHR_SELECT_END => {
+                let index = ((register - CHR_SELECT_START) >> 12) * 4 + (register & 0x3);
+                self.chr_banks[index as usize] = data as usize;
+                self.chr_generation += 1;
+            }
+            IRQ_LATCH => self.irq.latch = data,
+            IRQ_CONTROL => self.irq.write_control(data),
+            IRQ_ACKNOWLEDGE => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+}
+
+impl cartridge::Cartridge for Vrc6Cartridge {
+    fn cpu_tick(&mut self) {
+        self.irq.tick();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending
+    }
+}
+
+impl cartridge::CartridgeAudio for Vrc6Cartridge {
+    fn audio_tick(&mut self) {
+        self.audio.tick();
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+}
+
+impl cartridge::CpuInterface for Vrc6Cartridge {
+    fn ram_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.prg_ram[address as usize % self.prg_ram.len()])
+    }
+
+    fn ram_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        let len = self.prg_ram.len();
+        self.prg_ram[address as usize % len] = data;
+        Ok(())
+    }
+
+    fn lower_rom_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.prg_read(LOWER_ROM_ADDRESS + address))
+    }
+
+    fn lower_rom_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.register_write(LOWER_ROM_ADDRESS + address, data);
+        Ok(())
+    }
+
+    fn upper_rom_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.prg_read(UPPER_ROM_ADDRESS + address))
+    }
+
+    fn upper_rom_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        self.register_write(UPPER_ROM_ADDRESS + address, data);
+        Ok(())
+    }
+}
+
+impl cartridge::PpuInterface for Vrc6Cartridge {
+    fn pattern_table_read(&mut self, address: Address) -> addressable::Result<u8> {
+        Ok(self.chr[self.chr_offset(address)])
+    }
+
+    fn pattern_table_write(&mut self, address: Address, data: u8) -> addressable::Result<()> {
+        if !self.chr_is_ram {
+            return Err(addressable::Error::IllegalWrite(address));
+        }
+        let offset = self.chr_offset(address);
+        self.chr[offset] = data;
+        Ok(())
+    }
+
+    fn name_table_read(&mut self, address: Address, ram: &mut NesVram) -> addressable::Result<u8> {
+        ram.ppu_read8(self.mirroring.mirror(address))
+    }
+
+    fn name_table_write(&mut self, address: Address, data: u8, ram: &mut NesVram) -> addressable::Result<()> {
+        ram.ppu_write8(self.mirroring.mirror(address), data)
+    }
+
+    fn pattern_table_generation(&self) -> usize {
+        self.chr_generation
+    }
+}
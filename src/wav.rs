@@ -0,0 +1,112 @@
+use std::fs;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+
+use mixer;
+use mixer::{Channel, Mixer};
+
+const HEADER_SIZE: u32 = 44;
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+const BYTES_PER_SAMPLE: u32 = (BITS_PER_SAMPLE / 8) as u32;
+
+// Streams mono 16-bit PCM samples to a WAV file. The sizes in the header
+// aren't known until the recording stops, so they're filled in by finish.
+pub struct WavWriter {
+    file: BufWriter<fs::File>,
+    sample_rate: u32,
+    num_samples: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &str, sample_rate: u32) -> io::Result<Self> {
+        let file = try!(fs::File::create(path));
+        let mut writer = WavWriter {
+            file: BufWriter::new(file),
+            sample_rate: sample_rate,
+            num_samples: 0,
+        };
+        try!(writer.write_header());
+        Ok(writer)
+    }
+
+    pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            try!(write_u16(&mut self.file, mixer::to_i16(sample) as u16));
+        }
+        self.num_samples += samples.len() as u32;
+        Ok(())
+    }
+
+    // Writes the final sizes into the header and flushes the file
+    pub fn finish(mut self) -> io::Result<()> {
+        try!(self.file.seek(SeekFrom::Start(0)));
+        try!(self.write_header());
+        self.file.flush()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let data_size = self.num_samples * BYTES_PER_SAMPLE * CHANNELS as u32;
+        let block_align = BYTES_PER_SAMPLE as u16 * CHANNELS;
+        let byte_rate = self.sample_rate * block_align as u32;
+        let f = &mut self.file;
+
+        try!(f.write_all(b"RIFF"));
+        try!(write_u32(f, HEADER_SIZE - 8 + data_size));
+        try!(f.write_all(b"WAVE"));
+
+        try!(f.write_all(b"fmt "));
+        try!(write_u32(f, 16));
+        try!(write_u16(f, 1)); // PCM
+        try!(write_u16(f, CHANNELS));
+        try!(write_u32(f, self.sample_rate));
+        try!(write_u32(f, byte_rate));
+        try!(write_u16(f, block_align));
+        try!(write_u16(f, BITS_PER_SAMPLE));
+
+        try!(f.write_all(b"data"));
+        write_u32(f, data_size)
+    }
+}
+
+// Records each of the mixer's channel tracks to its own file, named after
+// the channel
+pub struct ChannelRecording {
+    writers: Vec<(Channel, WavWriter)>,
+}
+
+impl ChannelRecording {
+    pub fn create(prefix: &str, sample_rate: u32) -> io::Result<Self> {
+        let mut writers = Vec::new();
+        for &channel in mixer::CHANNELS.iter() {
+            let path = format!("{}-{}.wav", prefix, channel.name());
+            writers.push((channel, try!(WavWriter::create(&path, sample_rate))));
+        }
+        Ok(ChannelRecording {
+            writers: writers,
+        })
+    }
+
+    pub fn write_frame(&mut self, mixer: &Mixer) -> io::Result<()> {
+        for &mut (channel, ref mut writer) in self.writers.iter_mut() {
+            if let Some(samples) = mixer.channel_samples(channel) {
+                try!(writer.write_samples(samples));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        for (_, writer) in self.writers {
+            try!(writer.finish());
+        }
+        Ok(())
+    }
+}
+
+fn write_u16<W: Write>(w: &mut W, value: u16) -> io::Result<()> {
+    w.write_all(&[value as u8, (value >> 8) as u8])
+}
+
+fn write_u32<W: Write>(w: &mut W, value: u32) -> io::Result<()> {
+    w.write_all(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8])
+}
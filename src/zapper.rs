@@ -0,0 +1,87 @@
+use std::cmp;
+
+use input::InputDevice;
+use renderer::{Frame, Colour};
+use ppu;
+
+// Bits read from the Zapper's port: the light sensor is active low
+const LIGHT_NOT_SENSED: u8 = bit!(3);
+const TRIGGER_PULLED: u8 = bit!(4);
+
+// The sensor sees a few pixels either side of where it's aimed, and keeps
+// seeing light for a while after the beam has passed
+const SENSE_RADIUS: usize = 3;
+const LIGHT_SCANLINES: usize = 20;
+
+// Only the brighter colours are seen, the top two rows of the palette
+// except for its blacks
+const BRIGHT_LUMA: Colour = 2;
+const FIRST_BLACK_HUE: Colour = 0x0d;
+
+// The NES light gun. It's aimed at a pixel on screen, and senses light
+// while the PPU is drawing bright pixels near there.
+pub struct Zapper {
+    aim: Option<(usize, usize)>,
+    trigger: bool,
+    // the first scanline of the last frame with light where it's aimed
+    light_scanline: Option<usize>,
+    scanline: Option<usize>,
+}
+
+impl Zapper {
+    pub fn new() -> Self {
+        Zapper {
+            aim: None,
+            trigger: false,
+            light_scanline: None,
+            scanline: None,
+        }
+    }
+
+    fn senses_light(&self) -> bool {
+        match (self.light_scanline, self.scanline) {
+            (Some(light), Some(scanline)) => scanline >= light && scanline < light + LIGHT_SCANLINES,
+            _ => false,
+        }
+    }
+}
+
+fn is_bright(colour: Colour) -> bool {
+    let hue = colour & mask!(4);
+    let luma = (colour >> 4) & mask!(2);
+    hue < FIRST_BLACK_HUE && luma >= BRIGHT_LUMA
+}
+
+impl InputDevice for Zapper {
+    fn write_strobe(&mut self, _: bool) {}
+
+    fn read(&mut self) -> u8 {
+        let light = if self.senses_light() { 0 } else { LIGHT_NOT_SENSED };
+        let trigger = if self.trigger { TRIGGER_PULLED } else { 0 };
+        light | trigger
+    }
+
+    fn frame_rendered(&mut self, frame: &Frame) {
+        self.light_scanline = self.aim.and_then(|(x, y)| {
+            let left = x.saturating_sub(SENSE_RADIUS);
+            let right = cmp::min(x + SENSE_RADIUS, ppu::DISPLAY_WIDTH - 1);
+            let top = y.saturating_sub(SENSE_RADIUS);
+            let bottom = cmp::min(y + SENSE_RADIUS, ppu::DISPLAY_HEIGHT - 1);
+            (top..(bottom + 1)).find(|&row| {
+                (left..(right + 1)).any(|column| is_bright(frame.get_pixel(column, row)))
+            })
+        });
+    }
+
+    fn set_scanline(&mut self, scanline: Option<usize>) {
+        self.scanline = scanline;
+    }
+
+    fn set_pointer(&mut self, position: Option<(usize, usize)>) {
+        self.aim = position;
+    }
+
+    fn set_fire(&mut self, pressed: bool) {
+        self.trigger = pressed;
+    }
+}